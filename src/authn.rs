@@ -1,12 +1,19 @@
 use crate::{
     client::{Client, ClientConfig},
+    error::{BrigadeError, Result},
     meta::TypeMeta,
 };
-use anyhow::Error;
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use serde_with::*;
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    time::Instant,
+};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Token {
     #[serde(flatten)]
@@ -15,40 +22,278 @@ pub struct Token {
     pub value: String,
 }
 
+// PrincipalType is the kind of principal a token belongs to.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PrincipalType {
+    User,
+    ServiceAccount,
+    Root,
+}
+
+// PrincipalReference identifies a user, a service account or root.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct PrincipalReference {
+    #[serde(rename = "type")]
+    pub principal_type: PrincipalType,
+    pub id: String,
+}
+
+// UserSessionAuthDetails is returned when a user session is created. The
+// user has to visit the auth URL and log in with the identity provider
+// before the token can be used.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct UserSessionAuthDetails {
+    #[serde(rename = "authURL")]
+    pub auth_url: String,
+    pub token: String,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct UserSessionCreateOptions {
+    // Where the API server redirects the user's browser once they have
+    // logged in, such as the URL of a LoopbackListener.
+    #[serde(rename = "successURL")]
+    pub success_url: Option<String>,
+}
+
 pub struct SessionsClient {
     client: Client,
 }
 
 impl SessionsClient {
-    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self, Error> {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
         let client = Client::new(address, "sessions".to_string(), cfg, token)?;
         Ok(SessionsClient { client })
     }
 
-    pub async fn create_root_session(&self, pwd: String) -> Result<Token, Error> {
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("sessions"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    pub async fn create_root_session(&self, pwd: String) -> Result<Token> {
         let url = format!("{}/v2/{}", self.client.base_address, self.client.url_path);
-        let res = self
+        let req = self
             .client
             .req(Method::POST, &url, None)
             .query(&[("root", "true")])
-            .basic_auth(String::from("root"), Some(pwd))
-            .send()
+            .basic_auth(String::from("root"), Some(pwd));
+        self.client.send_json(req).await
+    }
+
+    // Starts a session for a user authenticated by the identity provider the
+    // API server is configured with. The returned token can't be used until
+    // the user has logged in at the returned auth URL; see
+    // wait_for_user_session.
+    pub async fn create_user_session(
+        &self,
+        opts: Option<UserSessionCreateOptions>,
+    ) -> Result<UserSessionAuthDetails> {
+        let url = format!("{}/v2/{}", self.client.base_address, self.client.url_path);
+        let req = self
+            .client
+            .req(Method::POST, &url, None)
+            .json(&opts.unwrap_or_default());
+        self.client.send_json(req).await
+    }
+
+    // Polls the API server every `interval` until the user session behind
+    // `token` is authenticated, and returns the logged in user. If `timeout`
+    // elapses first, it returns BrigadeError::Timeout.
+    pub async fn wait_for_user_session(
+        &self,
+        token: &str,
+        interval: Duration,
+        timeout: Option<Duration>,
+    ) -> Result<PrincipalReference> {
+        let sc = Self {
+            client: self.client.with_token(Some(token.to_string())),
+        };
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            match sc.whoami().await {
+                Ok(principal) => return Ok(principal),
+                Err(BrigadeError::Unauthenticated { .. }) => {}
+                Err(e) => return Err(e),
+            }
+            if let Some(deadline) = deadline {
+                if Instant::now() + interval > deadline {
+                    return Err(BrigadeError::Timeout);
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    // Returns the principal behind the client's token.
+    pub async fn whoami(&self) -> Result<PrincipalReference> {
+        let url = format!("{}/v2/whoami", self.client.base_address);
+        self.client
+            .send_json(self.client.req(Method::GET, &url, None))
+            .await
+    }
+
+    // Ends the session behind the client's token, which can't be used
+    // anymore afterwards.
+    pub async fn delete_session(&self) -> Result<()> {
+        let url = format!("{}/v2/session", self.client.base_address);
+        self.client
+            .send(self.client.req(Method::DELETE, &url, None))
             .await?;
-        let token: Token = serde_json::from_str(&res.text().await?.to_string())?;
-        Ok(token)
+        Ok(())
     }
 }
 
-#[tokio::test]
-async fn test_create_root_session() {
-    let cfg = ClientConfig {
-        allow_insecure_connections: true,
-    };
-    let sc = SessionsClient::new("https://localhost:8080".to_string(), cfg, None).unwrap();
-    let token = sc
-        .create_root_session("F00Bar!!!".to_string())
-        .await
-        .unwrap();
+// LoopbackListener catches the browser redirect at the end of a user
+// session login, so command line tools can tell when the user is done
+// without polling:
+//
+// let listener = LoopbackListener::bind().await?;
+// let opts = UserSessionCreateOptions {
+//     success_url: Some(listener.success_url()),
+// };
+// let details = sc.create_user_session(Some(opts)).await?;
+// println!("Log in at {}", details.auth_url);
+// listener.wait().await?;
+pub struct LoopbackListener {
+    listener: TcpListener,
+    address: SocketAddr,
+}
+
+// The page shown in the user's browser once they are logged in.
+const LOOPBACK_RESPONSE: &str =
+    "<html><body>You're logged in. You can close this window.</body></html>";
+
+impl LoopbackListener {
+    // Listens on a random port of the loopback interface.
+    pub async fn bind() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        Ok(Self { listener, address })
+    }
+
+    pub fn success_url(&self) -> String {
+        format!("http://{}/", self.address)
+    }
+
+    // Waits for the browser to be redirected to the success URL.
+    pub async fn wait(self) -> Result<()> {
+        let (mut socket, _) = self.listener.accept().await?;
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = socket.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            LOOPBACK_RESPONSE.len(),
+            LOOPBACK_RESPONSE
+        );
+        socket.write_all(response.as_bytes()).await?;
+        socket.shutdown().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{FakeServer, ROOT_PASSWORD};
+
+    #[tokio::test]
+    async fn test_create_root_session() {
+        let server = FakeServer::start().await;
+        let sc = SessionsClient::new(server.address(), ClientConfig::new(), None).unwrap();
+        let token = sc
+            .create_root_session(ROOT_PASSWORD.to_string())
+            .await
+            .unwrap();
+        assert!(!token.value.is_empty());
+
+        let err = sc
+            .create_root_session("wrong".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_user_session() {
+        let server = FakeServer::start().await;
+        let sc = SessionsClient::new(server.address(), ClientConfig::new(), None).unwrap();
+        let details = sc.create_user_session(None).await.unwrap();
+        assert!(details.auth_url.starts_with("https://"));
+
+        let interval = Duration::from_millis(10);
+        let err = sc
+            .wait_for_user_session(&details.token, interval, Some(Duration::from_millis(50)))
+            .await
+            .unwrap_err();
+        assert!(err.is_timeout());
+
+        server.authenticate_user_session(&details.token, "tony@example.com");
+        let principal = sc
+            .wait_for_user_session(&details.token, interval, None)
+            .await
+            .unwrap();
+        assert_eq!(principal.principal_type, PrincipalType::User);
+        assert_eq!(principal.id, "tony@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_loopback_listener() {
+        let server = FakeServer::start().await;
+        let sc = SessionsClient::new(server.address(), ClientConfig::new(), None).unwrap();
+        let listener = LoopbackListener::bind().await.unwrap();
+        let opts = UserSessionCreateOptions {
+            success_url: Some(listener.success_url()),
+        };
+        let details = sc.create_user_session(Some(opts)).await.unwrap();
 
-    println!("{:#?}", token);
+        // Stand in for the identity provider and the user's browser.
+        let success_url = server.authenticate_user_session(&details.token, "tony@example.com");
+        let browser =
+            tokio::spawn(async move { reqwest::get(success_url.unwrap()).await?.text().await });
+        listener.wait().await.unwrap();
+        assert!(browser.await.unwrap().unwrap().contains("logged in"));
+        sc.wait_for_user_session(&details.token, Duration::from_millis(10), None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_whoami_and_delete_session() {
+        let server = FakeServer::start().await;
+        let sc = server.brigade_client().authn().sessions();
+        let principal = sc.whoami().await.unwrap();
+        assert_eq!(principal.principal_type, PrincipalType::Root);
+
+        sc.delete_session().await.unwrap();
+        let err = sc.whoami().await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+    }
+
+    #[test]
+    fn test_principal_reference_serialization() {
+        let pr: PrincipalReference =
+            serde_json::from_str(r#"{"type":"SERVICE_ACCOUNT","id":"gateway"}"#).unwrap();
+        assert_eq!(pr.principal_type, PrincipalType::ServiceAccount);
+        assert_eq!(
+            serde_json::to_string(&pr).unwrap(),
+            r#"{"type":"SERVICE_ACCOUNT","id":"gateway"}"#
+        );
+    }
 }
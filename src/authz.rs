@@ -0,0 +1,459 @@
+use crate::{
+    authn::PrincipalReference,
+    client::{Client, ClientConfig},
+    error::Result,
+    meta::{APIVersion, Kind, List, ListOptions, TypeMeta},
+    pagination::{self, StreamOptions},
+};
+use futures::stream::BoxStream;
+use reqwest::{Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_with::*;
+use std::time::Duration;
+
+// Role is a set of system-wide permissions that can be granted to a user or
+// a service account.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Role {
+    // Manages the whole system, including role assignments.
+    #[serde(rename = "ADMIN")]
+    Admin,
+    // Reads every resource in the system.
+    #[serde(rename = "READER")]
+    Reader,
+    // Creates events for the source given as the scope of the assignment.
+    // This is typically granted to gateways.
+    #[serde(rename = "EVENT_CREATOR")]
+    EventCreator,
+    // Creates new projects.
+    #[serde(rename = "PROJECT_CREATOR")]
+    ProjectCreator,
+}
+
+// RoleAssignment grants a system role to a principal.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleAssignment {
+    #[serde(flatten)]
+    pub type_meta: Option<TypeMeta>,
+
+    pub role: Role,
+    pub principal: PrincipalReference,
+    // Narrows down the role. Only EVENT_CREATOR uses it, for the event
+    // source the principal may create events for.
+    pub scope: Option<String>,
+}
+
+impl RoleAssignment {
+    pub fn new(role: Role, principal: PrincipalReference) -> Self {
+        Self {
+            type_meta: Some(TypeMeta {
+                kind: Kind::RoleAssignment,
+                api_version: APIVersion::V2,
+            }),
+            role,
+            principal,
+            scope: None,
+        }
+    }
+
+    // Returns an EVENT_CREATOR assignment for the given event source.
+    pub fn event_creator(principal: PrincipalReference, source: String) -> Self {
+        Self {
+            scope: Some(source),
+            ..Self::new(Role::EventCreator, principal)
+        }
+    }
+}
+
+// RoleAssignmentsSelector filters the role assignments returned by
+// RoleAssignmentsClient::list.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct RoleAssignmentsSelector {
+    pub principal: Option<PrincipalReference>,
+    pub role: Option<Role>,
+}
+
+// ProjectRole is a set of permissions on a single project.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum ProjectRole {
+    // Updates and deletes the project, and manages its role assignments.
+    #[serde(rename = "PROJECT_ADMIN")]
+    ProjectAdmin,
+    // Updates the project and manages its secrets.
+    #[serde(rename = "PROJECT_DEVELOPER")]
+    ProjectDeveloper,
+    // Creates events for the project.
+    #[serde(rename = "PROJECT_USER")]
+    ProjectUser,
+}
+
+// ProjectRoleAssignment grants a project role to a principal.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRoleAssignment {
+    #[serde(flatten)]
+    pub type_meta: Option<TypeMeta>,
+
+    #[serde(rename = "projectID")]
+    pub project_id: String,
+    pub role: ProjectRole,
+    pub principal: PrincipalReference,
+}
+
+impl ProjectRoleAssignment {
+    pub fn new(project_id: String, role: ProjectRole, principal: PrincipalReference) -> Self {
+        Self {
+            type_meta: Some(TypeMeta {
+                kind: Kind::ProjectRoleAssignment,
+                api_version: APIVersion::V2,
+            }),
+            project_id,
+            role,
+            principal,
+        }
+    }
+}
+
+// ProjectRoleAssignmentsSelector filters the role assignments returned by
+// ProjectRoleAssignmentsClient::list.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ProjectRoleAssignmentsSelector {
+    pub principal: Option<PrincipalReference>,
+    pub role: Option<ProjectRole>,
+}
+
+#[derive(Clone)]
+pub struct RoleAssignmentsClient {
+    client: Client,
+}
+
+impl RoleAssignmentsClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, "role-assignments".to_string(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("role-assignments"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    // Grants a system role to a principal. Granting a role that is already
+    // granted is not an error.
+    pub async fn grant(&self, role_assignment: &RoleAssignment) -> Result<()> {
+        let mut role_assignment = role_assignment.clone();
+        role_assignment.type_meta = Some(TypeMeta {
+            kind: Kind::RoleAssignment,
+            api_version: APIVersion::V2,
+        });
+        let url = format!("{}/v2/{}", self.client.base_address, self.client.url_path);
+        self.client
+            .send(
+                self.client
+                    .req(Method::POST, &url, None)
+                    .json(&role_assignment),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Revokes a system role from a principal. The role and scope must match
+    // those of the assignment.
+    pub async fn revoke(&self, role_assignment: &RoleAssignment) -> Result<()> {
+        let url = format!("{}/v2/{}", self.client.base_address, self.client.url_path);
+        let mut req = self
+            .client
+            .req(Method::DELETE, &url, None)
+            .query(&[("role", &role_assignment.role)]);
+        req = principal_query(req, &role_assignment.principal);
+        if let Some(scope) = role_assignment.scope.as_ref() {
+            req = req.query(&[("scope", scope)]);
+        }
+        self.client.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn list(
+        &self,
+        sel: Option<RoleAssignmentsSelector>,
+        opts: Option<ListOptions>,
+    ) -> Result<List<RoleAssignment>> {
+        let mut req = self.client.list_req(opts);
+        if let Some(s) = sel {
+            if let Some(p) = s.principal.as_ref() {
+                req = principal_query(req, p);
+            }
+            if let Some(r) = s.role {
+                req = req.query(&[("role", r)]);
+            }
+        }
+        self.client.send_json(req).await
+    }
+
+    // Returns a stream over all role assignments matching the selector,
+    // fetching pages lazily as the stream is consumed.
+    pub fn list_all(
+        &self,
+        sel: Option<RoleAssignmentsSelector>,
+        opts: Option<ListOptions>,
+    ) -> BoxStream<'static, Result<RoleAssignment>> {
+        let rac = self.clone();
+        let pages = pagination::pages(opts, move |opts| {
+            let rac = rac.clone();
+            let sel = sel.clone();
+            async move { rac.list(sel, Some(opts)).await }
+        });
+        pagination::items(pages, StreamOptions::default())
+    }
+}
+
+#[derive(Clone)]
+pub struct ProjectRoleAssignmentsClient {
+    client: Client,
+}
+
+impl ProjectRoleAssignmentsClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, "projects".to_string(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("projects"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    // Grants a project role to a principal, on the project named in the
+    // assignment. Granting a role that is already granted is not an error.
+    pub async fn grant(&self, role_assignment: &ProjectRoleAssignment) -> Result<()> {
+        let mut role_assignment = role_assignment.clone();
+        role_assignment.type_meta = Some(TypeMeta {
+            kind: Kind::ProjectRoleAssignment,
+            api_version: APIVersion::V2,
+        });
+        let url = self.role_assignments_url(&role_assignment.project_id);
+        self.client
+            .send(
+                self.client
+                    .req(Method::POST, &url, None)
+                    .json(&role_assignment),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Revokes a project role from a principal.
+    pub async fn revoke(&self, role_assignment: &ProjectRoleAssignment) -> Result<()> {
+        let url = self.role_assignments_url(&role_assignment.project_id);
+        let req = self
+            .client
+            .req(Method::DELETE, &url, None)
+            .query(&[("role", &role_assignment.role)]);
+        let req = principal_query(req, &role_assignment.principal);
+        self.client.send(req).await?;
+        Ok(())
+    }
+
+    // Lists the role assignments of a project.
+    pub async fn list(
+        &self,
+        project_id: String,
+        sel: Option<ProjectRoleAssignmentsSelector>,
+        opts: Option<ListOptions>,
+    ) -> Result<List<ProjectRoleAssignment>> {
+        let url = self.role_assignments_url(&project_id);
+        let mut req = self.client.req(Method::GET, &url, opts);
+        if let Some(s) = sel {
+            if let Some(p) = s.principal.as_ref() {
+                req = principal_query(req, p);
+            }
+            if let Some(r) = s.role {
+                req = req.query(&[("role", r)]);
+            }
+        }
+        self.client.send_json(req).await
+    }
+
+    // Returns a stream over all role assignments of a project matching the
+    // selector, fetching pages lazily as the stream is consumed.
+    pub fn list_all(
+        &self,
+        project_id: String,
+        sel: Option<ProjectRoleAssignmentsSelector>,
+        opts: Option<ListOptions>,
+    ) -> BoxStream<'static, Result<ProjectRoleAssignment>> {
+        let prac = self.clone();
+        let pages = pagination::pages(opts, move |opts| {
+            let prac = prac.clone();
+            let project_id = project_id.clone();
+            let sel = sel.clone();
+            async move { prac.list(project_id, sel, Some(opts)).await }
+        });
+        pagination::items(pages, StreamOptions::default())
+    }
+
+    fn role_assignments_url(&self, project_id: &str) -> String {
+        format!(
+            "{}/v2/{}/{}/role-assignments",
+            self.client.base_address, self.client.url_path, project_id
+        )
+    }
+}
+
+fn principal_query(req: RequestBuilder, principal: &PrincipalReference) -> RequestBuilder {
+    req.query(&[("principalType", &principal.principal_type)])
+        .query(&[("principalID", &principal.id)])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        authn::PrincipalType, projects::Project, service_accounts::ServiceAccount,
+        testing::FakeServer,
+    };
+
+    #[tokio::test]
+    async fn test_grant_and_list_role_assignments() {
+        let server = FakeServer::start().await;
+        let bc = server.brigade_client();
+        let sa = ServiceAccount::new("gateway".to_string(), "A gateway".to_string());
+        bc.authn().service_accounts().create(&sa).await.unwrap();
+        let rac = bc.authz().role_assignments();
+
+        let reader = RoleAssignment::new(Role::Reader, gateway());
+        let creator = RoleAssignment::event_creator(gateway(), "brigade.sh/github".to_string());
+        rac.grant(&reader).await.unwrap();
+        rac.grant(&creator).await.unwrap();
+        rac.grant(&creator).await.unwrap();
+
+        let ral = rac.list(None, None).await.unwrap();
+        assert_eq!(ral.items.unwrap().len(), 2);
+
+        let sel = RoleAssignmentsSelector {
+            principal: Some(gateway()),
+            role: Some(Role::EventCreator),
+        };
+        let ral = rac.list(Some(sel), None).await.unwrap();
+        let items = ral.items.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].scope, Some("brigade.sh/github".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_role_assignment() {
+        let server = FakeServer::start().await;
+        let bc = server.brigade_client();
+        let sa = ServiceAccount::new("gateway".to_string(), "A gateway".to_string());
+        bc.authn().service_accounts().create(&sa).await.unwrap();
+        let rac = bc.authz().role_assignments();
+
+        let creator = RoleAssignment::event_creator(gateway(), "brigade.sh/github".to_string());
+        rac.grant(&creator).await.unwrap();
+        rac.revoke(&creator).await.unwrap();
+        let ral = rac.list(None, None).await.unwrap();
+        assert!(ral.items.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_grant_unknown_principal() {
+        let server = FakeServer::start().await;
+        let rac = server.brigade_client().authz().role_assignments();
+        let err = rac
+            .grant(&RoleAssignment::new(Role::Admin, gateway()))
+            .await
+            .unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_project_role_assignments() {
+        let server = FakeServer::start().await;
+        server.add_project(Project::new(
+            "hello-world".to_string(),
+            "A test project".to_string(),
+            "console.log('hello')".to_string(),
+        ));
+        let bc = server.brigade_client();
+        let sa = ServiceAccount::new("gateway".to_string(), "A gateway".to_string());
+        bc.authn().service_accounts().create(&sa).await.unwrap();
+        let prac = bc.projects().role_assignments();
+
+        let developer = ProjectRoleAssignment::new(
+            "hello-world".to_string(),
+            ProjectRole::ProjectDeveloper,
+            gateway(),
+        );
+        let user = ProjectRoleAssignment {
+            role: ProjectRole::ProjectUser,
+            ..developer.clone()
+        };
+        prac.grant(&developer).await.unwrap();
+        prac.grant(&user).await.unwrap();
+
+        let ral = prac
+            .list("hello-world".to_string(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(ral.items.unwrap().len(), 2);
+
+        prac.revoke(&developer).await.unwrap();
+        let sel = ProjectRoleAssignmentsSelector {
+            principal: Some(gateway()),
+            role: None,
+        };
+        let ral = prac
+            .list("hello-world".to_string(), Some(sel), None)
+            .await
+            .unwrap();
+        assert_eq!(ral.items.unwrap(), vec![user]);
+
+        let nope =
+            ProjectRoleAssignment::new("nope".to_string(), ProjectRole::ProjectAdmin, gateway());
+        assert!(prac.grant(&nope).await.unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn test_role_assignment_serialization() {
+        let ra = RoleAssignment::event_creator(gateway(), "brigade.sh/github".to_string());
+        let json = serde_json::to_value(&ra).unwrap();
+        assert_eq!(json["kind"], "RoleAssignment");
+        assert_eq!(json["role"], "EVENT_CREATOR");
+        assert_eq!(json["principal"]["type"], "SERVICE_ACCOUNT");
+        assert_eq!(json["scope"], "brigade.sh/github");
+
+        let pra = ProjectRoleAssignment::new(
+            "hello-world".to_string(),
+            ProjectRole::ProjectAdmin,
+            gateway(),
+        );
+        let json = serde_json::to_value(&pra).unwrap();
+        assert_eq!(json["kind"], "ProjectRoleAssignment");
+        assert_eq!(json["projectID"], "hello-world");
+        assert_eq!(json["role"], "PROJECT_ADMIN");
+    }
+
+    fn gateway() -> PrincipalReference {
+        PrincipalReference {
+            principal_type: PrincipalType::ServiceAccount,
+            id: "gateway".to_string(),
+        }
+    }
+}
@@ -1,28 +1,162 @@
+use crate::credentials::{CredentialProvider, StaticToken};
+use crate::error::{BrigadeError, Result};
 use crate::meta::{List, ListOptions};
-use anyhow::{Error, Result};
-use reqwest::{IntoUrl, Method, RequestBuilder};
+use crate::retry::{self, RetryPolicy};
+use crate::transport::{HttpTransport, ReqwestTransport};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, USER_AGENT},
+    IntoUrl, Method, Request, RequestBuilder, Response,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_with::*;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    future::Future,
+    path::Path,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+fn parse_header(name: &str, value: &str) -> Result<(HeaderName, HeaderValue)> {
+    let invalid = || BrigadeError::Config {
+        reason: format!("invalid header {:?}", name),
+    };
+    let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+    let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+    Ok((header_name, value))
+}
+
+// RequestBuilder needs a reqwest client, but requests are only built with
+// it. They are always sent through the client's transport, which applies
+// its own settings, so a single default client is shared for building.
+fn request_factory() -> &'static reqwest::Client {
+    static FACTORY: OnceLock<reqwest::Client> = OnceLock::new();
+    FACTORY.get_or_init(reqwest::Client::new)
+}
+
+const DEFAULT_USER_AGENT: &str = concat!("brigade-sdk-for-rust/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Debug, Clone)]
 pub struct ClientConfig {
     pub allow_insecure_connections: bool,
+    pub retry_policy: RetryPolicy,
+    // PEM encoded certificates (or bundles of certificates) trusted in
+    // addition to the system roots when verifying the API server.
+    pub root_certificates: Vec<Vec<u8>>,
+    // The identity presented to the API server for mutual TLS.
+    pub client_identity: Option<ClientIdentity>,
+    // How long to wait for a connection to the API server.
+    pub connect_timeout: Option<Duration>,
+    // How long to wait for the response headers, and then again for the
+    // response body. The first wait starts when the request is handed to
+    // the transport, so it includes connecting and the TLS handshake: a
+    // read timeout shorter than `connect_timeout` cuts connecting short.
+    pub read_timeout: Option<Duration>,
+    // The total time a request may take, from connecting until the
+    // response body has been read. Clients can override it per call with
+    // `with_timeout`.
+    pub timeout: Option<Duration>,
+    // The URL of a proxy every request is sent through. When unset, the
+    // HTTP_PROXY and HTTPS_PROXY environment variables are honored.
+    pub proxy: Option<String>,
+    // A comma separated list of hosts, domains and IP ranges that bypass
+    // the proxy, whether it is `proxy` or comes from the environment, in the
+    // same format as the NO_PROXY environment variable, which is used when
+    // this is unset.
+    pub no_proxy: Option<String>,
+    // The User-Agent header sent with every request. Defaults to the name
+    // and version of this SDK. Client::new rejects invalid values.
+    pub user_agent: Option<String>,
+    // Headers added to every request, such as a tenant identifier. They
+    // replace the built-in headers of the same name, including User-Agent.
+    // Client::new rejects invalid names and values.
+    pub default_headers: HashMap<String, String>,
 }
 
 impl ClientConfig {
     pub fn new() -> Self {
         Self {
             allow_insecure_connections: false,
+            retry_policy: RetryPolicy::new(),
+            root_certificates: Vec::new(),
+            client_identity: None,
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: None,
+            proxy: None,
+            no_proxy: None,
+            user_agent: None,
+            default_headers: HashMap::new(),
+        }
+    }
+
+    pub fn add_root_certificate_pem(&mut self, pem: &[u8]) {
+        self.root_certificates.push(pem.to_vec());
+    }
+
+    pub fn add_root_certificate_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.root_certificates.push(fs::read(path)?);
+        Ok(())
+    }
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub enum ClientIdentity {
+    // A PEM encoded certificate chain and PKCS #8 private key.
+    Pem { certificate: Vec<u8>, key: Vec<u8> },
+    // A DER encoded PKCS #12 archive, and the password protecting it.
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+impl ClientIdentity {
+    pub fn from_pem_files<P: AsRef<Path>>(certificate: P, key: P) -> Result<Self> {
+        Ok(ClientIdentity::Pem {
+            certificate: fs::read(certificate)?,
+            key: fs::read(key)?,
+        })
+    }
+
+    pub(crate) fn to_reqwest(&self) -> Result<reqwest::Identity> {
+        let identity = match self {
+            ClientIdentity::Pem { certificate, key } => {
+                reqwest::Identity::from_pkcs8_pem(certificate, key)?
+            }
+            ClientIdentity::Pkcs12 { der, password } => {
+                reqwest::Identity::from_pkcs12_der(der, password)?
+            }
+        };
+        Ok(identity)
+    }
+}
+
+// Private keys should never end up in logs, so only the kind of identity
+// is printed.
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientIdentity::Pem { .. } => write!(f, "ClientIdentity::Pem"),
+            ClientIdentity::Pkcs12 { .. } => write!(f, "ClientIdentity::Pkcs12"),
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Client {
-    pub rest: reqwest::Client,
+    pub transport: Arc<dyn HttpTransport>,
     pub config: ClientConfig,
     pub base_address: String,
-    pub token: Option<String>,
+    // Supplies the bearer token sent with every request.
+    pub credentials: Option<Arc<dyn CredentialProvider>>,
     pub url_path: String,
+    // Overrides the configured total timeout for requests sent by this client.
+    pub request_timeout: Option<Duration>,
 }
 
 impl Client {
@@ -31,17 +165,81 @@ impl Client {
         url_path: String,
         config: ClientConfig,
         token: Option<String>,
-    ) -> Result<Self, Error> {
-        let rest = reqwest::Client::builder()
-            .danger_accept_invalid_certs(config.allow_insecure_connections)
-            .build()?;
-        Ok(Self {
-            rest,
+    ) -> Result<Self> {
+        if let Some(ua) = config.user_agent.as_ref() {
+            parse_header(USER_AGENT.as_str(), ua)?;
+        }
+        for (k, v) in config.default_headers.iter() {
+            parse_header(k, v)?;
+        }
+        let transport = ReqwestTransport::new(&config)?;
+        Ok(Self::with_transport(
+            address,
             url_path,
             config,
-            base_address: address,
             token,
-        })
+            Arc::new(transport),
+        ))
+    }
+
+    // Builds a client that sends its requests through the given transport.
+    // Connection related settings from the configuration (TLS, proxy and
+    // connect timeout) are the responsibility of the transport.
+    pub fn with_transport(
+        address: String,
+        url_path: String,
+        config: ClientConfig,
+        token: Option<String>,
+        transport: Arc<dyn HttpTransport>,
+    ) -> Self {
+        Self {
+            transport,
+            url_path,
+            config,
+            base_address: address,
+            credentials: token.map(static_token),
+            request_timeout: None,
+        }
+    }
+
+    // Returns a client for another API resource. The returned client shares
+    // the connection pool, configuration and credentials of this one.
+    pub fn with_url_path(&self, url_path: &str) -> Self {
+        Self {
+            url_path: url_path.to_string(),
+            ..self.clone()
+        }
+    }
+
+    // Returns a client that authenticates with another token, but otherwise
+    // shares the connection pool and configuration of this one.
+    pub fn with_token(&self, token: Option<String>) -> Self {
+        Self {
+            credentials: token.map(static_token),
+            ..self.clone()
+        }
+    }
+
+    // Returns a client that asks the given provider for the token to send
+    // with every request, but otherwise shares the connection pool and
+    // configuration of this one.
+    pub fn with_credentials(&self, credentials: Arc<dyn CredentialProvider>) -> Self {
+        Self {
+            credentials: Some(credentials),
+            ..self.clone()
+        }
+    }
+
+    // Returns a client whose requests use the given total timeout instead of
+    // the configured one. This is useful for calls that are expected to take
+    // much longer (or much less) than usual:
+    //
+    // let events = client.with_timeout(Duration::from_secs(300)).list(None).await?;
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            request_timeout: Some(timeout),
+            ..self.clone()
+        }
     }
 }
 
@@ -52,11 +250,34 @@ impl Client {
         url: U,
         opts: Option<ListOptions>,
     ) -> RequestBuilder {
-        let mut req = self.rest.request(method, url);
+        let user_agent = self
+            .config
+            .user_agent
+            .as_deref()
+            .unwrap_or(DEFAULT_USER_AGENT);
+        let mut headers = HeaderMap::new();
+        let mut req = request_factory().request(method, url);
+        match HeaderValue::from_str(user_agent) {
+            Ok(v) => {
+                headers.insert(USER_AGENT, v);
+            }
+            // As with default headers below, only clients built with a
+            // custom transport get here.
+            Err(_) => req = req.header(USER_AGENT, user_agent),
+        }
+        for (k, v) in self.config.default_headers.iter() {
+            match parse_header(k, v) {
+                // Inserting replaces the built-in User-Agent, if configured.
+                Ok((name, value)) => {
+                    headers.insert(name, value);
+                }
+                // Only clients built with a custom transport get here; the
+                // error surfaces when the request is sent.
+                Err(_) => req = req.header(k.as_str(), v.as_str()),
+            }
+        }
+        req = req.headers(headers);
 
-        if let Some(token) = self.token.as_ref() {
-            req = req.bearer_auth(token);
-        };
         if let Some(opts) = opts {
             if let Some(c) = opts.continue_id {
                 req = req.query(&[("continue", c)]);
@@ -69,14 +290,9 @@ impl Client {
         req
     }
 
-    pub async fn get<T: Serialize + DeserializeOwned + Sized>(
-        &self,
-        id: String,
-    ) -> Result<T, Error> {
+    pub async fn get<T: Serialize + DeserializeOwned + Sized>(&self, id: String) -> Result<T> {
         let url = format!("{}/v2/{}/{}", self.base_address, self.url_path, id);
-        let res = self.req(Method::GET, &url, None).send().await?;
-        let obj: T = serde_json::from_str(&res.text().await?)?;
-        Ok(obj)
+        self.send_json(self.req(Method::GET, &url, None)).await
     }
 
     pub async fn create<
@@ -85,43 +301,34 @@ impl Client {
     >(
         &self,
         t: &T,
-    ) -> Result<U, Error> {
+    ) -> Result<U> {
         let url = format!("{}/v2/{}", self.base_address, self.url_path);
-        let res = self.req(Method::POST, &url, None).json(&t).send().await?;
-        let res = &res.text().await?;
-        println!("{}", res);
-        let obj: U = serde_json::from_str(res)?;
-        Ok(obj)
+        self.send_json(self.req(Method::POST, &url, None).json(&t))
+            .await
     }
 
     pub async fn update<T: Serialize + DeserializeOwned + Sized + Send + Clone>(
         &self,
         id: String,
         t: &T,
-    ) -> Result<T, Error> {
+    ) -> Result<T> {
         let url = format!("{}/v2/{}/{}", self.base_address, self.url_path, id);
-        let res = self.req(Method::PUT, &url, None).json(&t).send().await?;
-        let obj: T = serde_json::from_str(&res.text().await?)?;
-        Ok(obj)
+        self.send_json(self.req(Method::PUT, &url, None).json(&t))
+            .await
     }
 
-    pub async fn delete<T: Serialize + DeserializeOwned + Sized>(
-        &self,
-        id: String,
-    ) -> Result<(), Error> {
+    pub async fn delete<T: Serialize + DeserializeOwned + Sized>(&self, id: String) -> Result<()> {
         let url = format!("{}/v2/{}/{}", self.base_address, self.url_path, id);
-        self.req(Method::DELETE, &url, None).send().await?;
+        self.send(self.req(Method::DELETE, &url, None)).await?;
         Ok(())
     }
 
     pub async fn list<T: Serialize + DeserializeOwned + Sized>(
         &self,
         opts: Option<ListOptions>,
-    ) -> Result<List<T>, Error> {
+    ) -> Result<List<T>> {
         let url = format!("{}/v2/{}", self.base_address, self.url_path);
-        let res = self.req(Method::GET, &url, opts).send().await?;
-        let list: List<T> = serde_json::from_str(&res.text().await?)?;
-        Ok(list)
+        self.send_json(self.req(Method::GET, &url, opts)).await
     }
 
     // Specific clients might have selectors for listing clients.
@@ -132,6 +339,135 @@ impl Client {
         let url = format!("{}/v2/{}", self.base_address, self.url_path);
         self.req(Method::GET, &url, opts)
     }
+
+    // Sends the request and converts any non-success status code returned
+    // by the API server into the matching BrigadeError, so callers never
+    // try to deserialize an error body as the object they asked for.
+    //
+    // The bearer token is asked from the credential provider here, unless
+    // the request already carries its own credentials. If the API server
+    // rejects the token and the provider obtains a new one, the request is
+    // sent once more with it.
+    //
+    // Transient failures are retried according to the configured retry
+    // policy. Requests with a streaming body cannot be cloned, and are
+    // always sent exactly once.
+    pub async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let mut req = req.build()?;
+        if let Some(t) = self.request_timeout.or(self.config.timeout) {
+            *req.timeout_mut() = Some(t);
+        }
+        let credentials = match self.credentials.as_ref() {
+            Some(c) if !req.headers().contains_key(AUTHORIZATION) => c,
+            _ => return self.send_with_retries(req).await,
+        };
+        let token = credentials.token().await?;
+        let next = req.try_clone();
+        set_bearer_token(&mut req, token.as_deref())?;
+        let res = self.send_with_retries(req).await;
+        let mut next = match (&res, next) {
+            (Err(BrigadeError::Unauthenticated { .. }), Some(next)) => next,
+            _ => return res,
+        };
+        if !credentials.refresh(token.as_deref()).await? {
+            return res;
+        }
+        set_bearer_token(&mut next, credentials.token().await?.as_deref())?;
+        self.send_with_retries(next).await
+    }
+
+    async fn send_with_retries(&self, mut req: Request) -> Result<Response> {
+        let policy = &self.config.retry_policy;
+        let retryable = policy.allows(req.method());
+        let mut attempt = 1;
+        loop {
+            let next = match retryable && attempt < policy.max_attempts {
+                true => req.try_clone(),
+                false => None,
+            };
+            let outcome = self.execute(req).await;
+            let next = match next {
+                Some(next) => next,
+                None => return self.check_status(outcome?).await,
+            };
+            let delay = match &outcome {
+                Ok(res) if retry::is_retryable_status(res.status()) => {
+                    policy.backoff(attempt, retry::retry_after(res.headers()))
+                }
+                Err(err) if retry::is_retryable_error(err) => policy.backoff(attempt, None),
+                _ => return self.check_status(outcome?).await,
+            };
+            tokio::time::sleep(delay).await;
+            req = next;
+            attempt += 1;
+        }
+    }
+
+    pub async fn send_json<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T> {
+        let obj: T = serde_json::from_str(&self.send_text(req).await?)?;
+        Ok(obj)
+    }
+
+    pub async fn send_text(&self, req: RequestBuilder) -> Result<String> {
+        let res = self.send(req).await?;
+        self.with_read_timeout(res.text()).await
+    }
+
+    // The transport doesn't report when the connection is made, so the read
+    // timeout covers connecting as well.
+    async fn execute(&self, req: Request) -> Result<Response> {
+        match self.config.read_timeout {
+            Some(t) => match tokio::time::timeout(t, self.transport.execute(req)).await {
+                Ok(res) => res,
+                Err(_) => Err(BrigadeError::Timeout),
+            },
+            None => self.transport.execute(req).await,
+        }
+    }
+
+    async fn check_status(&self, res: Response) -> Result<Response> {
+        let status = res.status();
+        if status.is_success() {
+            return Ok(res);
+        }
+        let body = self.with_read_timeout(res.text()).await?;
+        Err(BrigadeError::from_response(status, &body))
+    }
+
+    async fn with_read_timeout<T, F>(&self, f: F) -> Result<T>
+    where
+        F: Future<Output = reqwest::Result<T>>,
+    {
+        match self.config.read_timeout {
+            Some(t) => match tokio::time::timeout(t, f).await {
+                Ok(res) => Ok(res?),
+                Err(_) => Err(BrigadeError::Timeout),
+            },
+            None => Ok(f.await?),
+        }
+    }
+}
+
+fn static_token(token: String) -> Arc<dyn CredentialProvider> {
+    Arc::new(StaticToken::new(token))
+}
+
+fn set_bearer_token(req: &mut Request, token: Option<&str>) -> Result<()> {
+    match token {
+        Some(t) => {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", t)).map_err(|_| {
+                BrigadeError::Unauthenticated {
+                    reason: "the token contains invalid characters".to_string(),
+                }
+            })?;
+            value.set_sensitive(true);
+            req.headers_mut().insert(AUTHORIZATION, value);
+        }
+        None => {
+            req.headers_mut().remove(AUTHORIZATION);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -140,37 +476,40 @@ mod test {
         authn::{SessionsClient, Token},
         meta::{APIVersion, Kind, TypeMeta},
         projects::Project,
+        testing::{FakeServer, ROOT_PASSWORD},
     };
 
     use super::*;
 
     #[tokio::test]
     async fn test_get_project() {
-        let cl = get_client("projects".to_string()).await;
-        let project = cl.get::<Project>("hello-world".to_string()).await.unwrap();
-        println!("{:#?}", project);
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let cl = get_client(&server, "projects".to_string()).await;
+        let project = cl
+            .get::<Project>("hello-rust-sdk".to_string())
+            .await
+            .unwrap();
+        assert_eq!(project, new_project());
     }
 
     #[tokio::test]
     async fn test_create_project() {
-        let cl = get_client("projects".to_string()).await;
-        let script = r#"
-        console.log("Hello, World!")
-    "#
-        .to_string();
-        let mut project = Project::new(
-            String::from("hello-rust-sdk"),
-            String::from("A project created from the Brigade Rust SDK"),
-            script,
-        );
+        let server = FakeServer::start().await;
+        let cl = get_client(&server, "projects".to_string()).await;
+        let mut project = new_project();
         ensure_project_meta(&mut project);
         let project = cl.create::<Project, Project>(&project).await.unwrap();
-        println!("{:#?}", project);
+        assert_eq!(project.metadata.id, "hello-rust-sdk");
+        assert!(project.metadata.created.is_some());
+        assert_eq!(server.projects().len(), 1);
     }
 
     #[tokio::test]
     async fn test_update_project() {
-        let cl = get_client("projects".to_string()).await;
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let cl = get_client(&server, "projects".to_string()).await;
         let mut project = cl
             .get::<Project>("hello-rust-sdk".to_string())
             .await
@@ -180,45 +519,286 @@ mod test {
         cl.update(project.metadata.id.clone(), &project)
             .await
             .unwrap();
+        assert_eq!(
+            server.projects()[0].description,
+            Some("totally new descrption".to_string())
+        );
     }
 
     #[tokio::test]
     async fn test_delete_project() {
-        let cl = get_client("projects".to_string()).await;
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let cl = get_client(&server, "projects".to_string()).await;
         cl.delete::<Project>("hello-rust-sdk".to_string())
             .await
             .unwrap();
+        assert!(server.projects().is_empty());
     }
 
     #[tokio::test]
     async fn test_list_projects() {
-        let cl = get_client("projects".to_string()).await;
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let cl = get_client(&server, "projects".to_string()).await;
         let projects = cl.list::<Project>(None).await.unwrap();
-        println!("{:#?}", projects);
+        assert_eq!(projects.items, Some(vec![new_project()]));
+    }
+
+    #[tokio::test]
+    async fn test_error_status() {
+        let body = r#"{"type":"Project","id":"missing","reason":""}"#;
+        let address = serve(vec![("404 Not Found", body)]).await;
+        let cl = Client::new(address, "projects".to_string(), ClientConfig::new(), None).unwrap();
+        let err = cl.get::<Project>("missing".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_status() {
+        let address = serve(vec![
+            ("503 Service Unavailable", "{}"),
+            ("502 Bad Gateway", "{}"),
+            ("200 OK", r#"{"metadata":{}}"#),
+        ])
+        .await;
+        let cfg = ClientConfig {
+            retry_policy: RetryPolicy {
+                base_backoff: std::time::Duration::from_millis(1),
+                ..RetryPolicy::new()
+            },
+            ..ClientConfig::new()
+        };
+        let cl = Client::new(address, "projects".to_string(), cfg, None).unwrap();
+        cl.list::<Project>(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_non_idempotent() {
+        let address = serve(vec![
+            ("503 Service Unavailable", "{}"),
+            ("200 OK", r#"{"metadata":{}}"#),
+        ])
+        .await;
+        let cl = Client::new(address, "projects".to_string(), ClientConfig::new(), None).unwrap();
+        let req = cl.req(
+            Method::POST,
+            format!("{}/v2/projects", cl.base_address),
+            None,
+        );
+        let err = cl.send(req).await.unwrap_err();
+        assert_eq!(err.status(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn test_root_certificates() {
+        let mut cfg = ClientConfig::new();
+        cfg.add_root_certificate_file("testdata/tls.crt").unwrap();
+        Client::new(String::new(), String::new(), cfg, None).unwrap();
+
+        let mut cfg = ClientConfig::new();
+        cfg.add_root_certificate_pem(b"-----BEGIN CERTIFICATE-----\nnot a certificate\n");
+        assert!(Client::new(String::new(), String::new(), cfg, None).is_err());
     }
 
-    async fn get_client(url_path: String) -> Client {
-        let address = "https://localhost:8080";
+    #[test]
+    fn test_client_identity() {
         let cfg = ClientConfig {
-            allow_insecure_connections: true,
+            client_identity: Some(
+                ClientIdentity::from_pem_files("testdata/tls.crt", "testdata/tls.key").unwrap(),
+            ),
+            ..ClientConfig::new()
         };
-        let token = get_token(String::from(address), cfg.clone()).await;
-        Client::new(
-            String::from(address),
-            String::from(url_path),
-            cfg,
-            Some(token.value),
-        )
-        .unwrap()
+        Client::new(String::new(), String::new(), cfg, None).unwrap();
     }
 
-    async fn get_token(address: String, cfg: ClientConfig) -> Token {
-        let sc = SessionsClient::new(String::from(address), cfg.clone(), None).unwrap();
-        let token = sc
-            .create_root_session("F00Bar!!!".to_string())
+    #[tokio::test]
+    async fn test_timeouts() {
+        let address = serve_nothing().await;
+        let cfg = ClientConfig {
+            read_timeout: Some(Duration::from_millis(50)),
+            retry_policy: RetryPolicy::disabled(),
+            ..ClientConfig::new()
+        };
+        let cl = Client::new(address.clone(), "projects".to_string(), cfg, None).unwrap();
+        let err = cl.list::<Project>(None).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Timeout));
+
+        let cfg = ClientConfig {
+            timeout: Some(Duration::from_secs(60)),
+            retry_policy: RetryPolicy::disabled(),
+            ..ClientConfig::new()
+        };
+        let cl = Client::new(address, "projects".to_string(), cfg, None).unwrap();
+        let err = cl
+            .with_timeout(Duration::from_millis(50))
+            .list::<Project>(None)
             .await
+            .unwrap_err();
+        assert!(err.is_timeout());
+    }
+
+    #[test]
+    fn test_default_headers() {
+        let mut cfg = ClientConfig::new();
+        cfg.default_headers
+            .insert("x-tenant".to_string(), "acme".to_string());
+        let cl = Client::new(String::new(), String::new(), cfg.clone(), None).unwrap();
+        let req = cl
+            .req(Method::GET, "http://localhost", None)
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["x-tenant"], "acme");
+        assert_eq!(req.headers()["user-agent"], DEFAULT_USER_AGENT);
+
+        cfg.user_agent = Some("my-gateway/1.0".to_string());
+        let cl = Client::new(String::new(), String::new(), cfg.clone(), None).unwrap();
+        let req = cl
+            .req(Method::GET, "http://localhost", None)
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["user-agent"], "my-gateway/1.0");
+
+        cfg.default_headers
+            .insert("User-Agent".to_string(), "my-proxy/2.0".to_string());
+        let cl = Client::new(String::new(), String::new(), cfg.clone(), None).unwrap();
+        let req = cl
+            .req(Method::GET, "http://localhost", None)
+            .build()
             .unwrap();
-        token
+        let agents: Vec<_> = req.headers().get_all("user-agent").iter().collect();
+        assert_eq!(agents, vec!["my-proxy/2.0"]);
+
+        let mut bad = cfg.clone();
+        bad.user_agent = Some("my-gateway\n/1.0".to_string());
+        let err = Client::new(String::new(), String::new(), bad, None).err();
+        assert!(matches!(err, Some(BrigadeError::Config { .. })));
+
+        cfg.default_headers
+            .insert("x tenant".to_string(), "acme".to_string());
+        let err = Client::new(String::new(), String::new(), cfg, None).err();
+        assert!(matches!(err, Some(BrigadeError::Config { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        let proxy = serve(vec![("404 Not Found", "{}")]).await;
+        let server = serve(vec![("200 OK", r#"{"metadata":{}}"#)]).await;
+        let cfg = ClientConfig {
+            proxy: Some(proxy),
+            no_proxy: Some("brigade.example.com".to_string()),
+            retry_policy: RetryPolicy::disabled(),
+            ..ClientConfig::new()
+        };
+        let cl = Client::new(server.clone(), "projects".to_string(), cfg.clone(), None).unwrap();
+        let err = cl.list::<Project>(None).await.unwrap_err();
+        assert!(err.is_not_found());
+
+        let cfg = ClientConfig {
+            no_proxy: Some("127.0.0.1".to_string()),
+            ..cfg
+        };
+        let cl = Client::new(server, "projects".to_string(), cfg, None).unwrap();
+        cl.list::<Project>(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_no_proxy_with_env_proxy() {
+        let proxy = serve(vec![("404 Not Found", "{}")]).await;
+        let server = serve(vec![("200 OK", r#"{"metadata":{}}"#)]).await;
+        let env = |var: &str| match var {
+            "HTTP_PROXY" => Some(proxy.clone()),
+            _ => None,
+        };
+        let cfg = ClientConfig {
+            retry_policy: RetryPolicy::disabled(),
+            ..ClientConfig::new()
+        };
+        let new_client = |cfg: &ClientConfig| {
+            let transport = ReqwestTransport::with_env(cfg, env).unwrap();
+            Client::with_transport(
+                server.clone(),
+                "projects".to_string(),
+                cfg.clone(),
+                None,
+                Arc::new(transport),
+            )
+        };
+        let cfg = ClientConfig {
+            no_proxy: Some("brigade.example.com".to_string()),
+            ..cfg
+        };
+        let err = new_client(&cfg).list::<Project>(None).await.unwrap_err();
+        assert!(err.is_not_found());
+
+        let cfg = ClientConfig {
+            no_proxy: Some("127.0.0.1".to_string()),
+            ..cfg
+        };
+        new_client(&cfg).list::<Project>(None).await.unwrap();
+    }
+
+    // Accepts connections, but never answers.
+    async fn serve_nothing() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        address
+    }
+
+    // Answers one HTTP request per connection, with the given status lines
+    // and bodies, in order.
+    async fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+                let res = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(res.as_bytes()).await.unwrap();
+            }
+        });
+        address
+    }
+
+    async fn get_client(server: &FakeServer, url_path: String) -> Client {
+        let address = server.address();
+        let cfg = ClientConfig::new();
+        let token = get_token(address.clone(), cfg.clone()).await;
+        Client::new(address, url_path, cfg, Some(token.value)).unwrap()
+    }
+
+    async fn get_token(address: String, cfg: ClientConfig) -> Token {
+        let sc = SessionsClient::new(address, cfg, None).unwrap();
+        sc.create_root_session(ROOT_PASSWORD.to_string())
+            .await
+            .unwrap()
+    }
+
+    fn new_project() -> Project {
+        let script = r#"
+        console.log("Hello, World!")
+    "#
+        .to_string();
+        Project::new(
+            String::from("hello-rust-sdk"),
+            String::from("A project created from the Brigade Rust SDK"),
+            script,
+        )
     }
 
     fn ensure_project_meta(project: &mut Project) {
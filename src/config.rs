@@ -0,0 +1,100 @@
+use crate::error::{BrigadeError, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+// The environment variables read by BrigadeClient::from_env and
+// BrigadeClient::from_vars.
+pub const SERVER_ENV_VAR: &str = "BRIGADE_SERVER";
+pub const TOKEN_ENV_VAR: &str = "BRIGADE_TOKEN";
+pub const INSECURE_ENV_VAR: &str = "BRIGADE_INSECURE";
+
+// BrigConfig is the configuration file written by `brig login`, which holds
+// the address of the API server and the token of the current session.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BrigConfig {
+    pub api_address: String,
+    #[serde(default)]
+    pub api_token: String,
+    #[serde(default)]
+    pub ignore_cert_errors: bool,
+}
+
+impl BrigConfig {
+    // Reads the configuration file from its default location,
+    // ~/.brigade/config.
+    pub fn load() -> Result<Self> {
+        Self::load_file(Self::default_path()?)
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config: BrigConfig = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if config.api_address.is_empty() {
+            return Err(BrigadeError::Config {
+                reason: "the brig config file has no API address".to_string(),
+            });
+        }
+        Ok(config)
+    }
+
+    pub fn default_path() -> Result<PathBuf> {
+        let home = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .ok_or_else(|| BrigadeError::Config {
+                reason: "could not find the home directory".to_string(),
+            })?;
+        Ok(PathBuf::from(home).join(".brigade").join("config"))
+    }
+
+    // Returns the token, or None if brig isn't logged in.
+    pub fn token(&self) -> Option<String> {
+        Some(self.api_token.clone()).filter(|t| !t.is_empty())
+    }
+}
+
+// Parses the value of a boolean environment variable. Unset and empty
+// variables are false.
+pub(crate) fn parse_flag(var: &str, value: Option<&str>) -> Result<bool> {
+    match value.unwrap_or_default().to_lowercase().as_str() {
+        "" | "0" | "false" | "no" => Ok(false),
+        "1" | "true" | "yes" => Ok(true),
+        v => Err(BrigadeError::Config {
+            reason: format!("{} must be true or false, not {:?}", var, v),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_load_brig_config() {
+        let path = env::temp_dir().join(format!("brig-config-{}", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"apiAddress":"https://brigade.example.com","apiToken":"t0ken","ignoreCertErrors":true}"#,
+        )
+        .unwrap();
+        let config = BrigConfig::load_file(&path).unwrap();
+        assert_eq!(config.api_address, "https://brigade.example.com");
+        assert_eq!(config.token(), Some("t0ken".to_string()));
+        assert!(config.ignore_cert_errors);
+
+        fs::write(&path, r#"{"apiAddress":""}"#).unwrap();
+        let err = BrigConfig::load_file(&path).unwrap_err();
+        assert!(matches!(err, BrigadeError::Config { .. }));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert!(!parse_flag(INSECURE_ENV_VAR, None).unwrap());
+        assert!(parse_flag(INSECURE_ENV_VAR, Some("TRUE")).unwrap());
+        assert!(!parse_flag(INSECURE_ENV_VAR, Some("no")).unwrap());
+        assert!(parse_flag(INSECURE_ENV_VAR, Some("maybe")).is_err());
+    }
+}
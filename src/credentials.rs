@@ -0,0 +1,279 @@
+use crate::{
+    authn::SessionsClient,
+    client::{Client, ClientConfig},
+    error::Result,
+};
+use async_trait::async_trait;
+use std::{path::PathBuf, time::SystemTime};
+use tokio::{fs, sync::Mutex};
+
+// CredentialProvider supplies the bearer token a Client sends with every
+// request. The client asks for the token each time it sends a request, so
+// providers can rotate tokens without rebuilding any client.
+//
+// When the API server rejects a token as invalid, the client calls
+// `refresh`, and if the provider obtained a new token, sends the request
+// once more with it.
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    // Returns the token to send with the next request, or None to send it
+    // unauthenticated.
+    async fn token(&self) -> Result<Option<String>>;
+
+    // Called with the token the API server just rejected. Returns true if a
+    // different token is now available.
+    async fn refresh(&self, _rejected: Option<&str>) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+// StaticToken always returns the same token.
+pub struct StaticToken {
+    token: String,
+}
+
+impl StaticToken {
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for StaticToken {
+    async fn token(&self) -> Result<Option<String>> {
+        Ok(Some(self.token.clone()))
+    }
+}
+
+type Lookup = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+// EnvToken reads the token from an environment variable on every request.
+// Requests are sent unauthenticated while the variable is unset or empty.
+pub struct EnvToken {
+    var: String,
+    lookup: Lookup,
+}
+
+impl EnvToken {
+    pub fn new(var: String) -> Self {
+        Self::with_lookup(var, |var| std::env::var(var).ok())
+    }
+
+    // Like new, but looks the variable up with `lookup` instead of in the
+    // process environment.
+    pub fn with_lookup<F>(var: String, lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            var,
+            lookup: Box::new(lookup),
+        }
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for EnvToken {
+    async fn token(&self) -> Result<Option<String>> {
+        Ok((self.lookup)(&self.var).filter(|t| !t.is_empty()))
+    }
+
+    async fn refresh(&self, rejected: Option<&str>) -> Result<bool> {
+        Ok(self.token().await?.as_deref() != rejected)
+    }
+}
+
+// FileToken reads the token from a file, such as a Kubernetes secret
+// mounted in a pod, and reads it again whenever the file changes. Leading
+// and trailing whitespace is ignored.
+pub struct FileToken {
+    path: PathBuf,
+    cache: Mutex<Option<(SystemTime, String)>>,
+}
+
+impl FileToken {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    async fn read(&self, force: bool) -> Result<String> {
+        let modified = fs::metadata(&self.path).await?.modified()?;
+        let mut cache = self.cache.lock().await;
+        if let Some((m, token)) = cache.as_ref() {
+            if *m == modified && !force {
+                return Ok(token.clone());
+            }
+        }
+        let token = fs::read_to_string(&self.path).await?.trim().to_string();
+        *cache = Some((modified, token.clone()));
+        Ok(token)
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for FileToken {
+    async fn token(&self) -> Result<Option<String>> {
+        Ok(Some(self.read(false).await?))
+    }
+
+    // Some filesystems only track modification times to the second, so the
+    // file is read again regardless.
+    async fn refresh(&self, rejected: Option<&str>) -> Result<bool> {
+        Ok(Some(self.read(true).await?.as_str()) != rejected)
+    }
+}
+
+// RootSessionCredentials logs in as root with a password, and logs in again
+// whenever the API server rejects the session token, for example after the
+// session expired.
+pub struct RootSessionCredentials {
+    sessions: SessionsClient,
+    password: String,
+    token: Mutex<Option<String>>,
+}
+
+impl RootSessionCredentials {
+    pub fn new(address: String, cfg: ClientConfig, password: String) -> Result<Self> {
+        Ok(Self {
+            sessions: SessionsClient::new(address, cfg, None)?,
+            password,
+            token: Mutex::new(None),
+        })
+    }
+
+    // Logs in through the given client, sharing its connection pool.
+    pub fn from_client(client: &Client, password: String) -> Self {
+        Self {
+            sessions: SessionsClient::from_client(&client.with_token(None)),
+            password,
+            token: Mutex::new(None),
+        }
+    }
+
+    async fn login(&self) -> Result<String> {
+        let token = self
+            .sessions
+            .create_root_session(self.password.clone())
+            .await?;
+        Ok(token.value)
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for RootSessionCredentials {
+    async fn token(&self) -> Result<Option<String>> {
+        let mut token = self.token.lock().await;
+        if token.is_none() {
+            *token = Some(self.login().await?);
+        }
+        Ok(token.clone())
+    }
+
+    async fn refresh(&self, rejected: Option<&str>) -> Result<bool> {
+        let mut token = self.token.lock().await;
+        // Another request may have logged in again already.
+        if token.as_deref() == rejected {
+            *token = Some(self.login().await?);
+        }
+        Ok(token.as_deref() != rejected)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::TOKEN_ENV_VAR,
+        error::BrigadeError,
+        sdk::BrigadeClient,
+        testing::{FakeServer, ROOT_PASSWORD},
+    };
+    use std::{collections::HashMap, sync::Arc};
+
+    #[tokio::test]
+    async fn test_static_token() {
+        let server = FakeServer::start().await;
+        let creds = StaticToken::new(server.root_token());
+        let bc =
+            BrigadeClient::with_credentials(server.address(), ClientConfig::new(), Arc::new(creds))
+                .unwrap();
+        bc.projects().list(None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_env_token() {
+        let server = FakeServer::start().await;
+        let env = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let lookup = {
+            let env = env.clone();
+            move |var: &str| env.lock().unwrap().get(var).cloned()
+        };
+        let creds = EnvToken::with_lookup(TOKEN_ENV_VAR.to_string(), lookup);
+        let bc =
+            BrigadeClient::with_credentials(server.address(), ClientConfig::new(), Arc::new(creds))
+                .unwrap();
+        let err = bc.projects().list(None, None).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+
+        env.lock()
+            .unwrap()
+            .insert(TOKEN_ENV_VAR.to_string(), server.root_token());
+        bc.projects().list(None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_token() {
+        let server = FakeServer::start().await;
+        let path = std::env::temp_dir().join(format!("brigade-token-{}", std::process::id()));
+        std::fs::write(&path, "expired\n").unwrap();
+        let creds = Arc::new(FileToken::new(&path));
+        let bc =
+            BrigadeClient::with_credentials(server.address(), ClientConfig::new(), creds.clone())
+                .unwrap();
+        let err = bc.projects().list(None, None).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+
+        // The rotated token is picked up, even if the modification time
+        // didn't change.
+        let token = server.root_token();
+        std::fs::write(&path, format!("{}\n", token)).unwrap();
+        bc.projects().list(None, None).await.unwrap();
+        assert_eq!(creds.token().await.unwrap(), Some(token));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_root_session_credentials() {
+        let server = FakeServer::start().await;
+        let creds = Arc::new(
+            RootSessionCredentials::new(
+                server.address(),
+                ClientConfig::new(),
+                ROOT_PASSWORD.to_string(),
+            )
+            .unwrap(),
+        );
+        let bc =
+            BrigadeClient::with_credentials(server.address(), ClientConfig::new(), creds.clone())
+                .unwrap();
+        bc.projects().list(None, None).await.unwrap();
+        let first = creds.token().await.unwrap();
+
+        // Logging out ends the session, so the next request logs in again.
+        bc.authn().sessions().delete_session().await.unwrap();
+        bc.projects().list(None, None).await.unwrap();
+        assert_ne!(creds.token().await.unwrap(), first);
+
+        let creds =
+            RootSessionCredentials::new(server.address(), ClientConfig::new(), "wrong".to_string())
+                .unwrap();
+        let bc =
+            BrigadeClient::with_credentials(server.address(), ClientConfig::new(), Arc::new(creds))
+                .unwrap();
+        let err = bc.projects().list(None, None).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+    }
+}
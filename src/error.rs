@@ -1,32 +1,346 @@
+use reqwest::StatusCode;
+use serde::Deserialize;
 use std::error;
 use std::fmt;
 
-// TODO
-// figure out a way to add custom
-// Brigade errors here.
+pub type Result<T, E = BrigadeError> = std::result::Result<T, E>;
+
 #[derive(Debug)]
 pub enum BrigadeError {
-    Reqwest(reqwest::Error),
+    /// The request was malformed or failed validation. `details` contains
+    /// one entry per invalid field, as reported by the API server.
+    BadRequest {
+        reason: String,
+        details: Vec<String>,
+    },
+    /// The request did not carry valid credentials.
+    Unauthenticated {
+        reason: String,
+    },
+    /// The principal behind the credentials is not allowed to do this.
+    Forbidden {
+        reason: String,
+    },
+    NotFound {
+        object_type: String,
+        id: String,
+        reason: String,
+    },
+    Conflict {
+        object_type: String,
+        id: String,
+        reason: String,
+    },
+    /// The API server failed to handle the request (5xx).
+    ServerError {
+        status: StatusCode,
+        reason: String,
+    },
+    /// Any other non-success status code.
+    UnexpectedStatus {
+        status: StatusCode,
+        body: String,
+    },
+    /// The request could not be sent, or the response could not be read.
+    Transport(reqwest::Error),
+    /// The API server did not respond within the configured read timeout.
+    Timeout,
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    /// A local file, such as a secrets file, could not be parsed.
+    Parse {
+        reason: String,
+    },
+    /// The connection settings are missing or invalid.
+    Config {
+        reason: String,
+    },
+}
+
+// The API server returns errors as JSON objects. Depending on the error
+// kind, only some of these fields are set.
+#[derive(Deserialize, Default)]
+struct ErrorBody {
+    #[serde(rename = "type")]
+    object_type: Option<String>,
+    id: Option<String>,
+    reason: Option<String>,
+    details: Option<ErrorDetails>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorDetails {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl BrigadeError {
+    /// Builds an error from a non-success status code and the body
+    /// returned by the API server.
+    pub fn from_response(status: StatusCode, body: &str) -> Self {
+        let eb: ErrorBody = serde_json::from_str(body).unwrap_or_default();
+        let reason = eb.reason.unwrap_or_default();
+        let details = match eb.details {
+            Some(ErrorDetails::One(d)) => vec![d],
+            Some(ErrorDetails::Many(d)) => d,
+            None => vec![],
+        };
+        match status {
+            StatusCode::BAD_REQUEST => BrigadeError::BadRequest { reason, details },
+            StatusCode::UNAUTHORIZED => BrigadeError::Unauthenticated { reason },
+            StatusCode::FORBIDDEN => BrigadeError::Forbidden { reason },
+            StatusCode::NOT_FOUND => BrigadeError::NotFound {
+                object_type: eb.object_type.unwrap_or_default(),
+                id: eb.id.unwrap_or_default(),
+                reason,
+            },
+            StatusCode::CONFLICT => BrigadeError::Conflict {
+                object_type: eb.object_type.unwrap_or_default(),
+                id: eb.id.unwrap_or_default(),
+                reason,
+            },
+            s if s.is_server_error() => BrigadeError::ServerError { status, reason },
+            _ => BrigadeError::UnexpectedStatus {
+                status,
+                body: body.to_string(),
+            },
+        }
+    }
+
+    /// Returns the HTTP status code for errors returned by the API server.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            BrigadeError::BadRequest { .. } => Some(StatusCode::BAD_REQUEST),
+            BrigadeError::Unauthenticated { .. } => Some(StatusCode::UNAUTHORIZED),
+            BrigadeError::Forbidden { .. } => Some(StatusCode::FORBIDDEN),
+            BrigadeError::NotFound { .. } => Some(StatusCode::NOT_FOUND),
+            BrigadeError::Conflict { .. } => Some(StatusCode::CONFLICT),
+            BrigadeError::ServerError { status, .. } => Some(*status),
+            BrigadeError::UnexpectedStatus { status, .. } => Some(*status),
+            BrigadeError::Transport(err) => err.status(),
+            BrigadeError::Timeout
+            | BrigadeError::Json(_)
+            | BrigadeError::Io(_)
+            | BrigadeError::Parse { .. }
+            | BrigadeError::Config { .. } => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, BrigadeError::NotFound { .. })
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, BrigadeError::Conflict { .. })
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            BrigadeError::Timeout => true,
+            BrigadeError::Transport(err) => err.is_timeout(),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for BrigadeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            BrigadeError::Reqwest(err) => write!(f, "reqwest error: {}", err.to_string()),
+            BrigadeError::BadRequest { reason, details } => {
+                write!(f, "bad request")?;
+                write_reason(f, reason)?;
+                if !details.is_empty() {
+                    write!(f, " ({})", details.join("; "))?;
+                }
+                Ok(())
+            }
+            BrigadeError::Unauthenticated { reason } => {
+                write!(f, "could not authenticate the request")?;
+                write_reason(f, reason)
+            }
+            BrigadeError::Forbidden { reason } => {
+                write!(f, "forbidden")?;
+                write_reason(f, reason)
+            }
+            BrigadeError::NotFound {
+                object_type,
+                id,
+                reason,
+            } => {
+                write!(f, "{} \"{}\" not found", object_type, id)?;
+                write_reason(f, reason)
+            }
+            BrigadeError::Conflict {
+                object_type,
+                id,
+                reason,
+            } => {
+                write!(f, "conflict on {} \"{}\"", object_type, id)?;
+                write_reason(f, reason)
+            }
+            BrigadeError::ServerError { status, reason } => {
+                write!(f, "server error ({})", status)?;
+                write_reason(f, reason)
+            }
+            BrigadeError::UnexpectedStatus { status, body } => {
+                write!(f, "unexpected status {}", status)?;
+                write_reason(f, body)
+            }
+            BrigadeError::Transport(err) => write!(f, "transport error: {}", err),
+            BrigadeError::Timeout => write!(f, "timed out waiting for the API server"),
+            BrigadeError::Json(err) => write!(f, "json error: {}", err),
+            BrigadeError::Io(err) => write!(f, "io error: {}", err),
+            BrigadeError::Parse { reason } => {
+                write!(f, "parse error")?;
+                write_reason(f, reason)
+            }
+            BrigadeError::Config { reason } => {
+                write!(f, "invalid configuration")?;
+                write_reason(f, reason)
+            }
         }
     }
 }
 
+// The API server doesn't always give a reason, so the separator is only
+// written when there is one.
+fn write_reason(f: &mut fmt::Formatter, reason: &str) -> fmt::Result {
+    match reason.is_empty() {
+        true => Ok(()),
+        false => write!(f, ": {}", reason),
+    }
+}
+
 impl error::Error for BrigadeError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            BrigadeError::Reqwest(ref e) => Some(e),
+            BrigadeError::Transport(ref e) => Some(e),
+            BrigadeError::Json(ref e) => Some(e),
+            BrigadeError::Io(ref e) => Some(e),
+            _ => None,
         }
     }
 }
 
 impl From<reqwest::Error> for BrigadeError {
     fn from(err: reqwest::Error) -> BrigadeError {
-        BrigadeError::Reqwest(err)
+        BrigadeError::Transport(err)
+    }
+}
+
+impl From<serde_json::Error> for BrigadeError {
+    fn from(err: serde_json::Error) -> BrigadeError {
+        BrigadeError::Json(err)
+    }
+}
+
+impl From<std::io::Error> for BrigadeError {
+    fn from(err: std::io::Error) -> BrigadeError {
+        BrigadeError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bad_request_details() {
+        let body = r#"{"kind":"BadRequestError","apiVersion":"brigade.sh/v2","reason":"Validation failed","details":["metadata.id: Does not match pattern"]}"#;
+        let err = BrigadeError::from_response(StatusCode::BAD_REQUEST, body);
+        assert_eq!(
+            err.to_string(),
+            "bad request: Validation failed (metadata.id: Does not match pattern)"
+        );
+        match err {
+            BrigadeError::BadRequest { reason, details } => {
+                assert_eq!(reason, "Validation failed");
+                assert_eq!(details, vec!["metadata.id: Does not match pattern"]);
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_not_found() {
+        let body = r#"{"kind":"NotFoundError","apiVersion":"brigade.sh/v2","type":"Project","id":"hello-world","reason":""}"#;
+        let err = BrigadeError::from_response(StatusCode::NOT_FOUND, body);
+        assert!(err.is_not_found());
+        assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(err.to_string(), "Project \"hello-world\" not found");
+        match err {
+            BrigadeError::NotFound {
+                object_type, id, ..
+            } => {
+                assert_eq!(object_type, "Project");
+                assert_eq!(id, "hello-world");
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_unparseable_body() {
+        let err = BrigadeError::from_response(StatusCode::BAD_GATEWAY, "<html></html>");
+        assert_eq!(err.to_string(), "server error (502 Bad Gateway)");
+        match err {
+            BrigadeError::ServerError { status, reason } => {
+                assert_eq!(status, StatusCode::BAD_GATEWAY);
+                assert_eq!(reason, "");
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let err = BrigadeError::from_response(StatusCode::TOO_MANY_REQUESTS, "slow down");
+        assert_eq!(
+            err.to_string(),
+            "unexpected status 429 Too Many Requests: slow down"
+        );
+        match err {
+            BrigadeError::UnexpectedStatus { body, .. } => assert_eq!(body, "slow down"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_display_without_reason() {
+        let errors = vec![
+            (
+                BrigadeError::Unauthenticated {
+                    reason: String::new(),
+                },
+                "could not authenticate the request",
+            ),
+            (
+                BrigadeError::Forbidden {
+                    reason: String::new(),
+                },
+                "forbidden",
+            ),
+            (
+                BrigadeError::BadRequest {
+                    reason: String::new(),
+                    details: Vec::new(),
+                },
+                "bad request",
+            ),
+            (
+                BrigadeError::Conflict {
+                    object_type: "Event".to_string(),
+                    id: "abc".to_string(),
+                    reason: String::new(),
+                },
+                "conflict on Event \"abc\"",
+            ),
+        ];
+        for (err, expected) in errors {
+            assert_eq!(err.to_string(), expected);
+        }
+
+        let err = BrigadeError::Forbidden {
+            reason: "Not a project admin".to_string(),
+        };
+        assert_eq!(err.to_string(), "forbidden: Not a project admin");
     }
 }
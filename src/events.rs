@@ -1,13 +1,16 @@
 use crate::{
     client::Client,
     client::ClientConfig,
+    error::Result,
     meta::{APIVersion, Kind, List, ListOptions, ObjectMeta, TypeMeta},
+    pagination::{self, StreamOptions},
     worker::{Worker, WorkerPhase},
 };
-use anyhow::Error;
+use futures::stream::BoxStream;
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 use serde_with::*;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -22,12 +25,20 @@ pub struct Event {
     pub source: String,
     #[serde(rename = "type")]
     pub event_type: String,
+    pub qualifiers: Option<HashMap<String, String>>,
     pub labels: Option<HashMap<String, String>>,
     pub short_title: Option<String>,
     pub long_title: Option<String>,
     pub git: Option<GitDetails>,
+    pub source_state: Option<SourceState>,
     pub payload: Option<String>,
+    pub summary: Option<String>,
     pub worker: Option<Worker>,
+
+    // Fields this version of the SDK doesn't know about. They are kept so
+    // reading and writing back an event doesn't drop them.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl Event {
@@ -35,19 +46,32 @@ impl Event {
         Self {
             metadata: None,
             type_meta: None,
-            project_id: project_id,
+            project_id,
             source,
             event_type,
+            qualifiers: None,
             labels: None,
             short_title: None,
             long_title: None,
             git: None,
+            source_state: None,
             payload: None,
+            summary: None,
             worker: None,
+            extra: HashMap::new(),
         }
     }
 }
 
+// SourceState is opaque state a gateway attaches to an event, and updates
+// as the event is handled.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceState {
+    pub state: Option<HashMap<String, String>>,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -58,11 +82,37 @@ pub struct EventSubscription {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct EventsSelector {
     pub project_id: Option<String>,
     pub worker_phases: Option<Vec<WorkerPhase>>,
+    pub source: Option<String>,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    // Selects events whose qualifiers are exactly these.
+    pub qualifiers: Option<HashMap<String, String>>,
+    // Selects events that have all of these labels, and possibly others.
+    pub labels: Option<HashMap<String, String>>,
+    pub source_state: Option<HashMap<String, String>>,
+}
+
+impl EventsSelector {
+    // Selects the events whose worker has run to completion.
+    pub fn terminal() -> Self {
+        Self {
+            worker_phases: Some(WorkerPhase::terminal()),
+            ..Default::default()
+        }
+    }
+
+    // Selects the events whose worker is still pending or running.
+    pub fn non_terminal() -> Self {
+        Self {
+            worker_phases: Some(WorkerPhase::non_terminal()),
+            ..Default::default()
+        }
+    }
 }
 
 #[skip_serializing_none]
@@ -90,17 +140,30 @@ pub struct DeleteManyEventsResult {
     pub count: i64,
 }
 
+#[derive(Clone)]
 pub struct EventsClient {
     pub client: Client,
 }
 
 impl EventsClient {
-    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self, Error> {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
         let client = Client::new(address, "events".to_string(), cfg, token)?;
         Ok(Self { client })
     }
 
-    pub async fn get(&self, id: String) -> Result<Event, Error> {
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("events"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    pub async fn get(&self, id: String) -> Result<Event> {
         let event = self.client.get::<Event>(id).await?;
         Ok(event)
     }
@@ -109,48 +172,109 @@ impl EventsClient {
         &self,
         sel: Option<EventsSelector>,
         opts: Option<ListOptions>,
-    ) -> Result<List<Event>, Error> {
+    ) -> Result<List<Event>> {
         let mut req = self.client.list_req(opts);
-        if let Some(s) = sel {
-            if let Some(id) = s.project_id {
-                req = req.query(&[("projectID", id)]);
-            }
-            if let Some(p) = s.worker_phases {
-                // TODO
-                //
-                // There is an issue with serializing a Vec<WorkerPhase>, and the events selector
-                // doesn't currently work.
-                // req = req.query(&[("workerPhases", &WorkerPhase::vec_to_query_param(p)?)]);
-                // req = req.query(&[("workerPhases", "SUCCEEDED")]);
-            }
-        };
+        if let Some(s) = sel.as_ref() {
+            req = with_selector(req, s)?;
+        }
 
-        let res = req.send().await?;
-        let str = &res.text().await?;
-        let events: List<Event> = serde_json::from_str(str)?;
-        Ok(events)
+        self.client.send_json(req).await
+    }
+
+    // Returns a stream over all events matching the selector, fetching
+    // pages lazily, starting from `opts`, as the stream is consumed.
+    pub fn list_all(
+        &self,
+        sel: Option<EventsSelector>,
+        opts: Option<ListOptions>,
+    ) -> BoxStream<'static, Result<Event>> {
+        self.stream(sel, opts, StreamOptions::default())
     }
-    pub async fn create(&self, event: &Event) -> Result<List<Event>, Error> {
+
+    // Like list_all, with control over prefetching and the maximum number of
+    // events returned.
+    pub fn stream(
+        &self,
+        sel: Option<EventsSelector>,
+        opts: Option<ListOptions>,
+        stream_opts: StreamOptions,
+    ) -> BoxStream<'static, Result<Event>> {
+        // The inherent clone method creates events; this clones the client.
+        let ec = Clone::clone(self);
+        let pages = pagination::pages(opts, move |opts| {
+            let ec = Clone::clone(&ec);
+            let sel = sel.clone();
+            async move { ec.list(sel, Some(opts)).await }
+        });
+        pagination::items(pages, stream_opts)
+    }
+
+    pub async fn create(&self, event: &Event) -> Result<List<Event>> {
         let mut event = event.clone();
         self.ensure_event_meta(&mut event);
         let events = self.client.create::<Event, List<Event>>(&event).await?;
         Ok(events)
     }
 
-    pub async fn cancel(&self, id: String) -> Result<(), Error> {
+    pub async fn cancel(&self, id: String) -> Result<()> {
         let url = format!(
             "{}/v2/{}/{}/cancellation",
             self.client.base_address, self.client.url_path, id
         );
-        let res = self
-            .client
-            .req(reqwest::Method::PUT, &url, None)
-            .send()
+        self.client
+            .send(self.client.req(reqwest::Method::PUT, &url, None))
             .await?;
-        println!("{}: {}", res.status().to_string(), res.text().await?);
         Ok(())
     }
 
+    pub async fn delete(&self, id: String) -> Result<()> {
+        self.client.delete::<Event>(id).await
+    }
+
+    // Retries an event whose worker has finished, creating a new event
+    // handled with the same worker configuration as the original.
+    pub async fn retry(&self, id: String) -> Result<Event> {
+        let url = format!(
+            "{}/v2/{}/{}/retries",
+            self.client.base_address, self.client.url_path, id
+        );
+        self.client
+            .send_json(self.client.req(reqwest::Method::POST, &url, None))
+            .await
+    }
+
+    // Creates a new event from the fields of an existing one, handled with
+    // the project's current configuration.
+    pub async fn clone(&self, id: String) -> Result<Event> {
+        let url = format!(
+            "{}/v2/{}/{}/clones",
+            self.client.base_address, self.client.url_path, id
+        );
+        self.client
+            .send_json(self.client.req(reqwest::Method::POST, &url, None))
+            .await
+    }
+
+    // Cancels all events of a project whose workers are in one of the
+    // selected phases. The API server requires both the project and the
+    // phases, and only cancels events whose workers are pending or running.
+    pub async fn cancel_many(&self, sel: EventsSelector) -> Result<CancelManyEventsResult> {
+        let url = format!(
+            "{}/v2/{}/cancellations",
+            self.client.base_address, self.client.url_path
+        );
+        let req = with_selector(self.client.req(reqwest::Method::POST, &url, None), &sel)?;
+        self.client.send_json(req).await
+    }
+
+    // Deletes all events of a project whose workers are in one of the
+    // selected phases. As with cancel_many, both are required.
+    pub async fn delete_many(&self, sel: EventsSelector) -> Result<DeleteManyEventsResult> {
+        let url = format!("{}/v2/{}", self.client.base_address, self.client.url_path);
+        let req = with_selector(self.client.req(reqwest::Method::DELETE, &url, None), &sel)?;
+        self.client.send_json(req).await
+    }
+
     fn ensure_event_meta(&self, event: &mut Event) {
         event.type_meta = Some(TypeMeta {
             kind: Kind::Event,
@@ -159,88 +283,479 @@ impl EventsClient {
     }
 }
 
+// Adds the selector's criteria to the request's query string.
+fn with_selector(mut req: RequestBuilder, s: &EventsSelector) -> Result<RequestBuilder> {
+    if let Some(id) = s.project_id.as_ref() {
+        req = req.query(&[("projectID", id)]);
+    }
+    if let Some(p) = s.worker_phases.clone() {
+        req = req.query(&[("workerPhases", &WorkerPhase::vec_to_query_param(p)?)]);
+    }
+    if let Some(source) = s.source.as_ref() {
+        req = req.query(&[("source", source)]);
+    }
+    if let Some(t) = s.event_type.as_ref() {
+        req = req.query(&[("type", t)]);
+    }
+    if let Some(q) = s.qualifiers.as_ref() {
+        req = req.query(&[("qualifiers", map_to_query_param(q))]);
+    }
+    if let Some(l) = s.labels.as_ref() {
+        req = req.query(&[("labels", map_to_query_param(l))]);
+    }
+    if let Some(ss) = s.source_state.as_ref() {
+        req = req.query(&[("sourceState", map_to_query_param(ss))]);
+    }
+    Ok(req)
+}
+
+// Encodes a map as the `k1=v1,k2=v2` list the API server expects in query
+// strings. Keys are sorted, so the same map always yields the same query.
+fn map_to_query_param(map: &HashMap<String, String>) -> String {
+    let mut pairs: Vec<String> = map.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    pairs.sort();
+    pairs.join(",")
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
         authn::{SessionsClient, Token},
         client::ClientConfig,
+        error::BrigadeError,
+        meta::ListMeta,
+        projects::Project,
+        testing::{FakeServer, ROOT_PASSWORD},
+        transport::InMemoryTransport,
     };
+    use futures::TryStreamExt;
+    use reqwest::StatusCode;
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn test_get_event() {
-        let ec = get_events_client().await;
-        let e = ec
-            .get("c325bca8-c615-4061-88ab-25aab9000de7".to_string())
-            .await
-            .unwrap();
-        println!("{:#?}", e);
+        let (server, ec) = get_events_client().await;
+        let created = ec.create(&new_event("hello-world")).await.unwrap();
+        let id = event_id(&created.items.unwrap()[0]);
+        let e = ec.get(id.clone()).await.unwrap();
+        assert_eq!(event_id(&e), id);
+        assert_eq!(server.events(), vec![e]);
+
+        let err = ec.get("missing".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
     }
 
     #[tokio::test]
     async fn test_list_events() {
-        let ec = get_events_client().await;
+        let (_server, ec) = get_events_client().await;
+        ec.create(&new_event("hello-world")).await.unwrap();
+        ec.create(&new_event("goodbye-world")).await.unwrap();
         let el = ec.list(None, None).await.unwrap();
-        println!("{:#?}", el);
+        assert_eq!(el.items.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_all_events() {
+        let (_server, ec) = get_events_client().await;
+        for _ in 0..3 {
+            ec.create(&new_event("hello-world")).await.unwrap();
+            ec.create(&new_event("goodbye-world")).await.unwrap();
+        }
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(4),
+        };
+        let events: Vec<Event> = ec.list_all(None, Some(opts)).try_collect().await.unwrap();
+        assert_eq!(events.len(), 6);
+
+        let sel = EventsSelector {
+            project_id: Some(String::from("hello-world")),
+            ..Default::default()
+        };
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(1),
+        };
+        let events: Vec<Event> = ec
+            .list_all(Some(sel), Some(opts))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|e| e.project_id == "hello-world"));
     }
 
     #[tokio::test]
     async fn test_list_events_with_sel_project() {
-        let ec = get_events_client().await;
+        let (_server, ec) = get_events_client().await;
+        ec.create(&new_event("hello-world")).await.unwrap();
+        ec.create(&new_event("goodbye-world")).await.unwrap();
         let sel = EventsSelector {
             project_id: Some(String::from("hello-world")),
-            worker_phases: None,
+            ..Default::default()
         };
         let el = ec.list(Some(sel), None).await.unwrap();
-        println!("{:#?}", el);
+        let events = el.items.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].project_id, "hello-world");
     }
 
     #[tokio::test]
     async fn test_list_events_with_sel_phases() {
-        let ec = get_events_client().await;
+        let (_server, ec) = get_events_client().await;
+        ec.create(&new_event("hello-world")).await.unwrap();
         let sel = EventsSelector {
-            project_id: None,
             worker_phases: Some(vec![WorkerPhase::Succeeded]),
+            ..Default::default()
         };
         let el = ec.list(Some(sel), None).await.unwrap();
-        println!("{:#?}", el);
+        assert!(el.items.unwrap().is_empty());
+
+        let el = ec
+            .list(Some(EventsSelector::terminal()), None)
+            .await
+            .unwrap();
+        assert!(el.items.unwrap().is_empty());
+        let el = ec
+            .list(Some(EventsSelector::non_terminal()), None)
+            .await
+            .unwrap();
+        assert_eq!(el.items.unwrap().len(), 1);
+
+        let sel = EventsSelector {
+            worker_phases: Some(vec![WorkerPhase::Pending, WorkerPhase::Running]),
+            ..Default::default()
+        };
+        let el = ec.list(Some(sel), None).await.unwrap();
+        assert_eq!(el.items.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_events_with_sel_source_and_labels() {
+        let (_server, ec) = get_events_client().await;
+        let mut event = new_event("hello-world");
+        event.labels = Some(
+            vec![("branch".to_string(), "main".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        ec.create(&event).await.unwrap();
+        event.event_type = "other".to_string();
+        ec.create(&event).await.unwrap();
+        ec.create(&new_event("goodbye-world")).await.unwrap();
+
+        let sel = EventsSelector {
+            source: Some("rust-sdk".to_string()),
+            event_type: Some("rust-sdk-test".to_string()),
+            labels: event.labels.clone(),
+            ..Default::default()
+        };
+        let el = ec.list(Some(sel), None).await.unwrap();
+        let events = el.items.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].project_id, "hello-world");
+    }
+
+    #[tokio::test]
+    async fn test_list_events_with_sel_qualifiers() {
+        let (_server, ec) = get_events_client().await;
+        let qualifiers: HashMap<String, String> =
+            vec![("repo".to_string(), "brigadecore/brigade".to_string())]
+                .into_iter()
+                .collect();
+        let mut event = new_event("hello-world");
+        event.qualifiers = Some(qualifiers.clone());
+        event.summary = Some("A push".to_string());
+        ec.create(&event).await.unwrap();
+        ec.create(&new_event("hello-world")).await.unwrap();
+
+        let sel = EventsSelector {
+            qualifiers: Some(qualifiers.clone()),
+            ..Default::default()
+        };
+        let events = ec.list(Some(sel), None).await.unwrap().items.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].qualifiers, Some(qualifiers));
+        assert_eq!(events[0].summary, Some("A push".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_events_selector_query() {
+        let transport = InMemoryTransport::new();
+        transport
+            .push_json(
+                StatusCode::OK,
+                &List::<Event> {
+                    metadata: ListMeta {
+                        continue_id: None,
+                        remaining_item_count: None,
+                    },
+                    items: None,
+                },
+            )
+            .unwrap();
+        let client = Client::with_transport(
+            "https://brigade.example.com".to_string(),
+            "events".to_string(),
+            ClientConfig::new(),
+            None,
+            Arc::new(transport.clone()),
+        );
+        let map = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let sel = EventsSelector {
+            project_id: Some("hello-world".to_string()),
+            worker_phases: Some(vec![WorkerPhase::Failed, WorkerPhase::TimedOut]),
+            source: Some("brigade.sh/github".to_string()),
+            event_type: Some("push".to_string()),
+            qualifiers: Some(map(&[("repo", "brigadecore/brigade")])),
+            labels: Some(map(&[("branch", "main"), ("author", "tony")])),
+            source_state: Some(map(&[("state", "done")])),
+        };
+        EventsClient::from_client(&client)
+            .list(Some(sel), None)
+            .await
+            .unwrap();
+
+        let url = transport.requests()[0].url.clone();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["projectID"], "hello-world");
+        assert_eq!(query["workerPhases"], "FAILED,TIMED_OUT");
+        assert_eq!(query["source"], "brigade.sh/github");
+        assert_eq!(query["type"], "push");
+        assert_eq!(query["qualifiers"], "repo=brigadecore/brigade");
+        assert_eq!(query["labels"], "author=tony,branch=main");
+        assert_eq!(query["sourceState"], "state=done");
+    }
+
+    #[test]
+    fn test_event_round_trip() {
+        let json = serde_json::json!({
+            "kind": "Event",
+            "apiVersion": "brigade.sh/v2",
+            "metadata": {"id": "abc"},
+            "projectID": "hello-world",
+            "source": "brigade.sh/github",
+            "type": "push",
+            "qualifiers": {"repo": "brigadecore/brigade"},
+            "sourceState": {"state": {"check": "pending"}},
+            "summary": "All good",
+            "worker": {
+                "spec": {
+                    "timeoutDuration": "10m",
+                    "logLevel": "DEBUG",
+                    "jobPolicies": {"allowPrivileged": true, "allowDockerSocketMount": false},
+                    "kubernetes": {"imagePullSecrets": ["registry"]}
+                },
+                "status": {"phase": "RUNNING"}
+            },
+            "somethingNew": {"nested": [1, 2]}
+        });
+        let event: Event = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(event.type_meta.as_ref().unwrap().kind, Kind::Event);
+        assert_eq!(event.summary, Some("All good".to_string()));
+        assert_eq!(
+            event.qualifiers.as_ref().unwrap()["repo"],
+            "brigadecore/brigade"
+        );
+        let spec = &event.worker.as_ref().unwrap().spec;
+        assert_eq!(spec.timeout_duration, Some("10m".to_string()));
+        let policies = spec.job_policies.as_ref().unwrap();
+        assert_eq!(policies.allow_privileged, Some(true));
+        assert_eq!(policies.allow_docker_socket_mount, Some(false));
+        assert_eq!(event.extra.len(), 1);
+        assert!(event.extra.contains_key("somethingNew"));
+        assert_eq!(serde_json::to_value(&event).unwrap(), json);
     }
 
     #[tokio::test]
     async fn test_create_event() {
-        let ec = get_events_client().await;
-        let ev = Event::new(
-            "hello-world".to_string(),
-            "rust-sdk".to_string(),
-            "rust-sdk-test".to_string(),
+        let (_server, ec) = get_events_client().await;
+        let res = ec.create(&new_event("hello-world")).await.unwrap();
+        let events = res.items.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].project_id, "hello-world");
+        assert_eq!(
+            events[0].worker.as_ref().unwrap().status.phase,
+            Some(WorkerPhase::Pending)
         );
-        let res = ec.create(&ev).await.unwrap();
-        println!("{:#?}", res);
+
+        let err = ec.create(&new_event("missing")).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_create_event_fan_out() {
+        let (server, ec) = get_events_client().await;
+        let mut project = new_project("subscribed");
+        project.spec.event_subscriptions = Some(vec![EventSubscription {
+            source: "rust-sdk".to_string(),
+            types: vec!["rust-sdk-test".to_string()],
+            labels: HashMap::new(),
+        }]);
+        server.add_project(project);
+
+        let res = ec.create(&new_event("")).await.unwrap();
+        let events = res.items.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].project_id, "subscribed");
     }
 
     #[tokio::test]
     async fn test_cancel_event() {
-        let ec = get_events_client().await;
-        ec.cancel("7cf24d38-f2b2-4d0f-9aac-c0a07c01c78c".to_string())
+        let (server, ec) = get_events_client().await;
+        let created = ec.create(&new_event("hello-world")).await.unwrap();
+        let id = event_id(&created.items.unwrap()[0]);
+        ec.cancel(id.clone()).await.unwrap();
+        let phase = server.events()[0].worker.as_ref().unwrap().status.phase;
+        assert_eq!(phase, Some(WorkerPhase::Canceled));
+
+        let err = ec.cancel(id).await.unwrap_err();
+        assert!(err.is_conflict());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_many_events() {
+        let (server, ec) = get_events_client().await;
+        ec.create(&new_event("hello-world")).await.unwrap();
+        ec.create(&new_event("hello-world")).await.unwrap();
+        ec.create(&new_event("goodbye-world")).await.unwrap();
+
+        let sel = EventsSelector {
+            project_id: Some("hello-world".to_string()),
+            ..EventsSelector::non_terminal()
+        };
+        let res = ec.cancel_many(sel).await.unwrap();
+        assert_eq!(res.count, 2);
+        let canceled = server
+            .events()
+            .iter()
+            .filter(|e| e.worker.as_ref().unwrap().status.phase == Some(WorkerPhase::Canceled))
+            .count();
+        assert_eq!(canceled, 2);
+
+        let err = ec
+            .cancel_many(EventsSelector::non_terminal())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BrigadeError::BadRequest { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_delete_many_events() {
+        let (server, ec) = get_events_client().await;
+        let created = ec.create(&new_event("hello-world")).await.unwrap();
+        ec.cancel(event_id(&created.items.unwrap()[0]))
             .await
             .unwrap();
+        ec.create(&new_event("hello-world")).await.unwrap();
+        ec.create(&new_event("goodbye-world")).await.unwrap();
+
+        let sel = EventsSelector {
+            project_id: Some("hello-world".to_string()),
+            ..EventsSelector::terminal()
+        };
+        let res = ec.delete_many(sel).await.unwrap();
+        assert_eq!(res.count, 1);
+        assert_eq!(server.events().len(), 2);
+
+        let err = ec.delete_many(EventsSelector::default()).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::BadRequest { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_delete_event() {
+        let (server, ec) = get_events_client().await;
+        let created = ec.create(&new_event("hello-world")).await.unwrap();
+        let id = event_id(&created.items.unwrap()[0]);
+        ec.delete(id.clone()).await.unwrap();
+        assert!(server.events().is_empty());
+
+        let err = ec.delete(id).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_retry_event() {
+        let (server, ec) = get_events_client().await;
+        let created = ec.create(&new_event("hello-world")).await.unwrap();
+        let id = event_id(&created.items.unwrap()[0]);
+
+        // Only events that have finished can be retried.
+        let err = ec.retry(id.clone()).await.unwrap_err();
+        assert!(err.is_conflict());
+
+        ec.cancel(id.clone()).await.unwrap();
+        let retried = ec.retry(id.clone()).await.unwrap();
+        assert_ne!(event_id(&retried), id);
+        assert_eq!(retried.project_id, "hello-world");
+        assert_eq!(
+            retried.worker.as_ref().unwrap().status.phase,
+            Some(WorkerPhase::Pending)
+        );
+        assert_eq!(server.events().len(), 2);
+
+        let err = ec.retry("missing".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_clone_event() {
+        let (server, ec) = get_events_client().await;
+        let mut event = new_event("hello-world");
+        event.payload = Some("deploy".to_string());
+        let created = ec.create(&event).await.unwrap();
+        let id = event_id(&created.items.unwrap()[0]);
+
+        let cloned = ec.clone(id.clone()).await.unwrap();
+        assert_ne!(event_id(&cloned), id);
+        assert_eq!(cloned.payload, Some("deploy".to_string()));
+        assert_eq!(server.events().len(), 2);
+
+        let err = ec.clone("missing".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
     }
 
     async fn get_token(address: String, cfg: ClientConfig) -> Token {
-        let sc = SessionsClient::new(String::from(address), cfg.clone(), None).unwrap();
-        let token = sc
-            .create_root_session("F00Bar!!!".to_string())
+        let sc = SessionsClient::new(address, cfg, None).unwrap();
+        sc.create_root_session(ROOT_PASSWORD.to_string())
             .await
-            .unwrap();
-        token
+            .unwrap()
     }
 
-    async fn get_events_client() -> EventsClient {
-        let address = "https://localhost:8080";
-        let cfg = ClientConfig {
-            allow_insecure_connections: true,
-        };
-        let token = get_token(String::from(address), cfg.clone()).await;
-        EventsClient::new(String::from(address), cfg, Some(token.value)).unwrap()
+    async fn get_events_client() -> (FakeServer, EventsClient) {
+        let server = FakeServer::start().await;
+        server.add_project(new_project("hello-world"));
+        server.add_project(new_project("goodbye-world"));
+        let address = server.address();
+        let cfg = ClientConfig::new();
+        let token = get_token(address.clone(), cfg.clone()).await;
+        let ec = EventsClient::new(address, cfg, Some(token.value)).unwrap();
+        (server, ec)
+    }
+
+    fn new_project(id: &str) -> Project {
+        Project::new(
+            id.to_string(),
+            "A test project".to_string(),
+            "console.log('hello')".to_string(),
+        )
+    }
+
+    fn new_event(project_id: &str) -> Event {
+        Event::new(
+            project_id.to_string(),
+            "rust-sdk".to_string(),
+            "rust-sdk-test".to_string(),
+        )
+    }
+
+    fn event_id(event: &Event) -> String {
+        event.metadata.as_ref().unwrap().id.clone()
     }
 }
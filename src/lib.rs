@@ -1,9 +1,22 @@
 pub mod authn;
+pub mod authz;
 pub mod client;
+pub mod config;
 pub mod container;
+pub mod credentials;
 pub mod error;
 pub mod events;
 pub mod job;
 pub mod meta;
+pub mod pagination;
 pub mod projects;
+pub mod retry;
+pub mod sdk;
+pub mod secrets;
+pub mod service_accounts;
+pub mod system;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transport;
+pub mod users;
 pub mod worker;
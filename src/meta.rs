@@ -28,12 +28,17 @@ pub enum Kind {
     Token,
     Project,
     Event,
+    PingResponse,
+    ServiceAccount,
+    User,
+    RoleAssignment,
+    ProjectRoleAssignment,
 }
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct List<T: Serialize + Sized> {
+pub struct List<T> {
     pub metadata: ListMeta,
     pub items: Option<Vec<T>>,
 }
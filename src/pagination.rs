@@ -0,0 +1,208 @@
+use crate::{
+    error::Result,
+    meta::{List, ListOptions},
+};
+use futures::{
+    stream::{self, BoxStream},
+    Future, Stream, StreamExt, TryStreamExt,
+};
+
+// StreamOptions controls how the items of a paginated list are streamed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamOptions {
+    // Stops the stream after this many items. No further pages are fetched
+    // once the cap is reached, even when prefetching.
+    pub max_items: Option<usize>,
+    // Fetches the next page in the background while the items of the
+    // current one are consumed. This requires a Tokio runtime.
+    pub prefetch: bool,
+}
+
+// Returns a stream of pages, calling `fetch` with the options for each
+// page. The first page is fetched with `opts`, and every following page
+// with the `continue` value returned by the API server, until there are no
+// pages left. Pages are only fetched when the stream is polled.
+pub fn pages<T, F, Fut>(opts: Option<ListOptions>, fetch: F) -> impl Stream<Item = Result<Vec<T>>>
+where
+    F: Fn(ListOptions) -> Fut,
+    Fut: Future<Output = Result<List<T>>>,
+{
+    let opts = opts.unwrap_or(ListOptions {
+        continue_id: None,
+        limit: None,
+    });
+    stream::try_unfold((fetch, Some(opts)), |(fetch, next)| async move {
+        let opts = match next {
+            Some(opts) => opts,
+            None => return Ok(None),
+        };
+        let page = fetch(opts.clone()).await?;
+        let next = page
+            .metadata
+            .continue_id
+            .filter(|c| !c.is_empty())
+            .map(|c| ListOptions {
+                continue_id: Some(c),
+                limit: opts.limit,
+            });
+        Ok(Some((page.items.unwrap_or_default(), (fetch, next))))
+    })
+}
+
+// Flattens a stream of pages into a stream of items, applying the stream
+// options.
+pub fn items<T, S>(pages: S, stream_opts: StreamOptions) -> BoxStream<'static, Result<T>>
+where
+    T: Send + 'static,
+    S: Stream<Item = Result<Vec<T>>> + Send + 'static,
+{
+    let pages = match stream_opts.prefetch {
+        true => prefetch(pages, stream_opts.max_items),
+        false => pages.boxed(),
+    };
+    let items = pages
+        .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+        .try_flatten();
+    match stream_opts.max_items {
+        Some(n) => items.take(n).boxed(),
+        None => items.boxed(),
+    }
+}
+
+// Drives the page stream from a background task, one page ahead of the
+// consumer. The task stops after the first error, once it has sent
+// `max_items` items, or when the returned stream is dropped.
+fn prefetch<T, S>(pages: S, max_items: Option<usize>) -> BoxStream<'static, Result<Vec<T>>>
+where
+    T: Send + 'static,
+    S: Stream<Item = Result<Vec<T>>> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    tokio::spawn(async move {
+        futures::pin_mut!(pages);
+        let mut sent = 0;
+        while let Some(page) = pages.next().await {
+            let done = match &page {
+                Ok(items) => {
+                    sent += items.len();
+                    max_items.map(|n| sent >= n).unwrap_or(false)
+                }
+                Err(_) => true,
+            };
+            if tx.send(page).await.is_err() || done {
+                break;
+            }
+        }
+    });
+    stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|page| (page, rx))
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::BrigadeError, meta::ListMeta};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // Serves the numbers 0..total, in pages of `limit` items.
+    fn numbers(
+        total: usize,
+        calls: Arc<AtomicUsize>,
+    ) -> impl Stream<Item = Result<Vec<usize>>> + Send + 'static {
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(2),
+        };
+        pages(Some(opts), move |opts| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let start: usize = opts.continue_id.map(|c| c.parse().unwrap()).unwrap_or(0);
+                let end = (start + opts.limit.unwrap() as usize).min(total);
+                Ok(List {
+                    metadata: ListMeta {
+                        continue_id: if end < total {
+                            Some(end.to_string())
+                        } else {
+                            None
+                        },
+                        remaining_item_count: None,
+                    },
+                    items: Some((start..end).collect()),
+                })
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_items() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let all: Vec<usize> = items(numbers(5, calls.clone()), StreamOptions::default())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_max_items() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let opts = StreamOptions {
+            max_items: Some(3),
+            prefetch: false,
+        };
+        let all: Vec<usize> = items(numbers(100, calls.clone()), opts)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all, vec![0, 1, 2]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let opts = StreamOptions {
+            max_items: None,
+            prefetch: true,
+        };
+        let all: Vec<usize> = items(numbers(7, calls.clone()), opts)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all, (0..7).collect::<Vec<usize>>());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_max_items() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let opts = StreamOptions {
+            max_items: Some(3),
+            prefetch: true,
+        };
+        let all: Vec<usize> = items(numbers(100, calls.clone()), opts)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all, vec![0, 1, 2]);
+        // Give the background task a chance to fetch more than it should.
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_error() {
+        let pages = pages(None, |_| async {
+            Err::<List<usize>, _>(BrigadeError::Timeout)
+        });
+        let mut s = items(pages, StreamOptions::default());
+        assert!(matches!(s.next().await, Some(Err(BrigadeError::Timeout))));
+        assert!(s.next().await.is_none());
+    }
+}
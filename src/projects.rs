@@ -1,13 +1,17 @@
 use crate::{
+    authz::ProjectRoleAssignmentsClient,
     client::Client,
     client::ClientConfig,
+    error::Result,
     events::EventSubscription,
     meta::{APIVersion, Kind, List, ListOptions, ObjectMeta, TypeMeta},
+    pagination::{self, StreamOptions},
     worker::WorkerSpec,
 };
-use anyhow::{Error, Result};
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
 use serde_with::*;
+use std::time::Duration;
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -57,29 +61,42 @@ pub struct KubernetesDetails {
     namespace: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct ProjectsClient {
     pub client: Client,
 }
 
 impl ProjectsClient {
-    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self, Error> {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
         let client = Client::new(address, "projects".to_string(), cfg, token)?;
         Ok(Self { client })
     }
 
-    pub async fn get(&self, id: String) -> Result<Project, Error> {
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("projects"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    pub async fn get(&self, id: String) -> Result<Project> {
         let project = self.client.get::<Project>(id).await?;
         Ok(project)
     }
 
-    pub async fn create(&self, project: &Project) -> Result<Project, Error> {
+    pub async fn create(&self, project: &Project) -> Result<Project> {
         let mut project = project.clone();
         self.ensure_project_meta(&mut project);
         let project = self.client.create(&project).await?;
         Ok(project)
     }
 
-    pub async fn update(&self, project: &Project) -> Result<Project, Error> {
+    pub async fn update(&self, project: &Project) -> Result<Project> {
         let mut project = project.clone();
         self.ensure_project_meta(&mut project);
         let project = self
@@ -89,7 +106,7 @@ impl ProjectsClient {
         Ok(project)
     }
 
-    pub async fn delete(&self, id: String) -> Result<(), Error> {
+    pub async fn delete(&self, id: String) -> Result<()> {
         self.client.delete::<Project>(id).await?;
         Ok(())
     }
@@ -98,11 +115,43 @@ impl ProjectsClient {
         &self,
         _: Option<ProjectsSelector>,
         opts: Option<ListOptions>,
-    ) -> Result<List<Project>, Error> {
+    ) -> Result<List<Project>> {
         let projects = self.client.list(opts).await?;
         Ok(projects)
     }
 
+    // Returns a stream over all projects matching the selector, fetching
+    // pages lazily, starting from `opts`, as the stream is consumed.
+    pub fn list_all(
+        &self,
+        sel: Option<ProjectsSelector>,
+        opts: Option<ListOptions>,
+    ) -> BoxStream<'static, Result<Project>> {
+        self.stream(sel, opts, StreamOptions::default())
+    }
+
+    // Like list_all, with control over prefetching and the maximum number of
+    // projects returned.
+    pub fn stream(
+        &self,
+        sel: Option<ProjectsSelector>,
+        opts: Option<ListOptions>,
+        stream_opts: StreamOptions,
+    ) -> BoxStream<'static, Result<Project>> {
+        let pc = self.clone();
+        let pages = pagination::pages(opts, move |opts| {
+            let pc = pc.clone();
+            let sel = sel.clone();
+            async move { pc.list(sel, Some(opts)).await }
+        });
+        pagination::items(pages, stream_opts)
+    }
+
+    // Returns a client for the role assignments of projects.
+    pub fn role_assignments(&self) -> ProjectRoleAssignmentsClient {
+        ProjectRoleAssignmentsClient::from_client(&self.client)
+    }
+
     fn ensure_project_meta(&self, project: &mut Project) {
         project.type_meta = Some(TypeMeta {
             kind: Kind::Project,
@@ -119,67 +168,148 @@ impl ProjectsClient {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{authn::SessionsClient, authn::Token, client::ClientConfig};
+    use crate::{
+        authn::SessionsClient,
+        authn::Token,
+        client::ClientConfig,
+        testing::{FakeServer, ROOT_PASSWORD},
+    };
+    use futures::TryStreamExt;
 
     #[tokio::test]
     async fn test_get_project() {
-        let pc = get_projects_client().await;
-        let p = pc.get("hello-world".to_string()).await.unwrap();
-        println!("{:#?}", p);
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let pc = get_projects_client(&server).await;
+        let p = pc.get("hello-rust-sdk".to_string()).await.unwrap();
+        assert_eq!(p, new_project());
     }
 
     #[tokio::test]
     async fn test_create_project() {
-        let pc = get_projects_client().await;
-        let script = r#"
-        console.log("Hello, World!")
-    "#
-        .to_string();
-        let project = Project::new(
-            String::from("hello-rust-sdk"),
-            String::from("A project created from the Brigade Rust SDK"),
-            script,
-        );
-        let project = pc.create(&project).await.unwrap();
-        println!("{:#?}", project);
+        let server = FakeServer::start().await;
+        let pc = get_projects_client(&server).await;
+        let project = pc.create(&new_project()).await.unwrap();
+        assert_eq!(project.metadata.id, "hello-rust-sdk");
+        assert_eq!(project.type_meta.unwrap().kind, Kind::Project);
+
+        let err = pc.create(&new_project()).await.unwrap_err();
+        assert!(err.is_conflict());
     }
 
     #[tokio::test]
     async fn test_update_project() {
-        let pc = get_projects_client().await;
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let pc = get_projects_client(&server).await;
         let mut p = pc.get("hello-rust-sdk".to_string()).await.unwrap();
         p.description = Some("totally new descrption".to_string());
-        pc.update(&p).await.unwrap();
+        let p = pc.update(&p).await.unwrap();
+        assert_eq!(p.description, Some("totally new descrption".to_string()));
     }
 
     #[tokio::test]
     async fn test_delete_project() {
-        let pc = get_projects_client().await;
+        let server = FakeServer::start().await;
+        server.add_project(new_project());
+        let pc = get_projects_client(&server).await;
         pc.delete("hello-rust-sdk".to_string()).await.unwrap();
+        let err = pc.get("hello-rust-sdk".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
     }
 
     #[tokio::test]
     async fn test_list_projects() {
-        let pc = get_projects_client().await;
-        let pl = pc.list(None, None).await.unwrap();
-        println!("{:#?}", pl);
+        let server = FakeServer::start().await;
+        for i in 0..3 {
+            let mut p = new_project();
+            p.metadata.id = format!("project-{}", i);
+            server.add_project(p);
+        }
+        let pc = get_projects_client(&server).await;
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(2),
+        };
+        let pl = pc.list(None, Some(opts)).await.unwrap();
+        assert_eq!(pl.items.unwrap().len(), 2);
+        assert_eq!(pl.metadata.remaining_item_count, Some(1));
+
+        let opts = ListOptions {
+            continue_id: pl.metadata.continue_id,
+            limit: Some(2),
+        };
+        let pl = pc.list(None, Some(opts)).await.unwrap();
+        assert_eq!(pl.items.unwrap()[0].metadata.id, "project-2");
+        assert_eq!(pl.metadata.continue_id, None);
     }
 
-    async fn get_token(address: String, cfg: ClientConfig) -> Token {
-        let sc = SessionsClient::new(String::from(address), cfg.clone(), None).unwrap();
-        let token = sc
-            .create_root_session("F00Bar!!!".to_string())
+    #[tokio::test]
+    async fn test_list_all_projects() {
+        let server = FakeServer::start().await;
+        for i in 0..5 {
+            let mut p = new_project();
+            p.metadata.id = format!("project-{}", i);
+            server.add_project(p);
+        }
+        let pc = get_projects_client(&server).await;
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(2),
+        };
+        let projects: Vec<Project> = pc
+            .list_all(None, Some(opts.clone()))
+            .try_collect()
             .await
             .unwrap();
-        token
-    }
+        let ids: Vec<String> = projects.into_iter().map(|p| p.metadata.id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "project-0",
+                "project-1",
+                "project-2",
+                "project-3",
+                "project-4"
+            ]
+        );
 
-    async fn get_projects_client() -> ProjectsClient {
-        let address = "https://localhost:8080";
-        let cfg = ClientConfig {
-            allow_insecure_connections: true,
+        let stream_opts = StreamOptions {
+            max_items: Some(3),
+            prefetch: true,
         };
-        let token = get_token(String::from(address), cfg.clone()).await;
-        ProjectsClient::new(String::from(address), cfg, Some(token.value)).unwrap()
+        let projects: Vec<Project> = pc
+            .stream(None, Some(opts), stream_opts)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(projects.len(), 3);
+        assert_eq!(projects[2].metadata.id, "project-2");
+    }
+
+    async fn get_token(address: String, cfg: ClientConfig) -> Token {
+        let sc = SessionsClient::new(address, cfg, None).unwrap();
+        sc.create_root_session(ROOT_PASSWORD.to_string())
+            .await
+            .unwrap()
+    }
+
+    async fn get_projects_client(server: &FakeServer) -> ProjectsClient {
+        let address = server.address();
+        let cfg = ClientConfig::new();
+        let token = get_token(address.clone(), cfg.clone()).await;
+        ProjectsClient::new(address, cfg, Some(token.value)).unwrap()
+    }
+
+    fn new_project() -> Project {
+        let script = r#"
+        console.log("Hello, World!")
+    "#
+        .to_string();
+        Project::new(
+            String::from("hello-rust-sdk"),
+            String::from("A project created from the Brigade Rust SDK"),
+            script,
+        )
     }
 }
@@ -0,0 +1,170 @@
+use crate::error::BrigadeError;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use std::time::Duration;
+
+// RetryPolicy controls how the client retries requests that failed because
+// of a transient condition, such as a connection reset or a 502/503 returned
+// while the API server is being rolled out.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // The maximum number of times a request is sent, including the first
+    // attempt. A value of 1 disables retries.
+    pub max_attempts: u32,
+    // The backoff before the first retry. It doubles after every attempt.
+    pub base_backoff: Duration,
+    // The upper bound for the backoff, including any Retry-After value.
+    pub max_backoff: Duration,
+    // When enabled, a random delay between zero and the computed backoff is
+    // used, so clients that failed at the same time don't retry in lockstep.
+    pub jitter: bool,
+    // Non-idempotent requests (such as creating an event) are only retried
+    // when this is enabled, since the server may have already handled them.
+    pub retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            jitter: true,
+            retry_non_idempotent: false,
+        }
+    }
+
+    // Returns a policy that sends every request exactly once.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::new()
+        }
+    }
+
+    pub fn allows(&self, method: &Method) -> bool {
+        self.max_attempts > 1 && (self.retry_non_idempotent || is_idempotent(method))
+    }
+
+    // Returns how long to wait before the given retry. `attempt` is the
+    // number of attempts made so far.
+    pub fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(d) = retry_after {
+            return d.min(self.max_backoff);
+        }
+        let exp = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let backoff = self
+            .base_backoff
+            .checked_mul(exp)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        if self.jitter && !backoff.is_zero() {
+            rand::thread_rng().gen_range(Duration::ZERO..=backoff)
+        } else {
+            backoff
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+pub fn is_retryable_error(err: &BrigadeError) -> bool {
+    match err {
+        BrigadeError::Transport(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+        BrigadeError::Timeout => true,
+        _ => false,
+    }
+}
+
+// Parses the Retry-After header, which holds either a number of seconds
+// or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::{HeaderValue, RETRY_AFTER};
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::new()
+        };
+        assert_eq!(policy.backoff(1, None), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, None), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3, None), Duration::from_millis(400));
+        assert_eq!(policy.backoff(30, None), Duration::from_secs(5));
+        assert_eq!(
+            policy.backoff(1, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.backoff(1, Some(Duration::from_secs(60))),
+            Duration::from_secs(5)
+        );
+
+        let policy = RetryPolicy::new();
+        for attempt in 1..5 {
+            assert!(policy.backoff(attempt, None) <= Duration::from_millis(100 << (attempt - 1)));
+        }
+    }
+
+    #[test]
+    fn test_allows() {
+        let policy = RetryPolicy::new();
+        assert!(policy.allows(&Method::GET));
+        assert!(policy.allows(&Method::PUT));
+        assert!(!policy.allows(&Method::POST));
+
+        let policy = RetryPolicy {
+            retry_non_idempotent: true,
+            ..RetryPolicy::new()
+        };
+        assert!(policy.allows(&Method::POST));
+        assert!(!RetryPolicy::disabled().allows(&Method::GET));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        // A date in the past is ignored, and the regular backoff applies.
+        assert_eq!(retry_after(&headers), None);
+    }
+}
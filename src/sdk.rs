@@ -0,0 +1,245 @@
+use crate::{
+    authn::SessionsClient,
+    authz::{ProjectRoleAssignmentsClient, RoleAssignmentsClient},
+    client::{Client, ClientConfig},
+    config::{self, BrigConfig},
+    credentials::CredentialProvider,
+    error::{BrigadeError, Result},
+    events::EventsClient,
+    projects::ProjectsClient,
+    secrets::SecretsClient,
+    service_accounts::ServiceAccountsClient,
+    system::SystemClient,
+    transport::HttpTransport,
+    users::UsersClient,
+};
+use std::{path::Path, sync::Arc};
+
+// BrigadeClient is the entry point to the Brigade API. It builds a single
+// connection pool and holds a single set of credentials, which are shared
+// by every sub-client it hands out, so it should be created once and
+// reused.
+#[derive(Clone)]
+pub struct BrigadeClient {
+    client: Client,
+}
+
+impl BrigadeClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, String::new(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    // Builds a client that sends every request through the given transport,
+    // such as a transport::InMemoryTransport in unit tests.
+    pub fn with_transport(
+        address: String,
+        cfg: ClientConfig,
+        token: Option<String>,
+        transport: Arc<dyn HttpTransport>,
+    ) -> Self {
+        let client = Client::with_transport(address, String::new(), cfg, token, transport);
+        Self { client }
+    }
+
+    // Builds a client that asks the given provider for the token to send
+    // with every request.
+    pub fn with_credentials(
+        address: String,
+        cfg: ClientConfig,
+        credentials: Arc<dyn CredentialProvider>,
+    ) -> Result<Self> {
+        let client = Client::new(address, String::new(), cfg, None)?;
+        Ok(Self {
+            client: client.with_credentials(credentials),
+        })
+    }
+
+    // Builds a client from the BRIGADE_SERVER, BRIGADE_TOKEN and
+    // BRIGADE_INSECURE environment variables. Only BRIGADE_SERVER is
+    // required.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|var| std::env::var(var).ok())
+    }
+
+    // Like from_env, but looks the variables up with `vars`, for example in
+    // a map loaded from a file.
+    pub fn from_vars<F: Fn(&str) -> Option<String>>(vars: F) -> Result<Self> {
+        let var = |name: &str| vars(name).filter(|v| !v.is_empty());
+        let address = var(config::SERVER_ENV_VAR).ok_or_else(|| BrigadeError::Config {
+            reason: format!("{} is not set", config::SERVER_ENV_VAR),
+        })?;
+        let token = var(config::TOKEN_ENV_VAR);
+        let mut cfg = ClientConfig::new();
+        cfg.allow_insecure_connections = config::parse_flag(
+            config::INSECURE_ENV_VAR,
+            var(config::INSECURE_ENV_VAR).as_deref(),
+        )?;
+        Self::new(address, cfg, token)
+    }
+
+    // Builds a client with the address and token `brig login` saved in
+    // ~/.brigade/config, so it uses the same session as the CLI.
+    pub fn from_brig_config() -> Result<Self> {
+        Self::from_brig_config_file(BrigConfig::default_path()?)
+    }
+
+    pub fn from_brig_config_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let brig = BrigConfig::load_file(path)?;
+        let mut cfg = ClientConfig::new();
+        cfg.allow_insecure_connections = brig.ignore_cert_errors;
+        Self::new(brig.api_address.clone(), cfg, brig.token())
+    }
+
+    pub fn from_client(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub fn projects(&self) -> ProjectsClient {
+        ProjectsClient::from_client(&self.client)
+    }
+
+    pub fn events(&self) -> EventsClient {
+        EventsClient::from_client(&self.client)
+    }
+
+    pub fn secrets(&self) -> SecretsClient {
+        SecretsClient::from_client(&self.client)
+    }
+
+    pub fn authn(&self) -> AuthnClient {
+        AuthnClient {
+            client: self.client.clone(),
+        }
+    }
+
+    pub fn authz(&self) -> AuthzClient {
+        AuthzClient {
+            client: self.client.clone(),
+        }
+    }
+
+    pub fn core(&self) -> CoreClient {
+        CoreClient {
+            client: self.client.clone(),
+        }
+    }
+
+    pub fn system(&self) -> SystemClient {
+        SystemClient::from_client(&self.client)
+    }
+}
+
+// AuthnClient groups the clients for authentication related resources.
+#[derive(Clone)]
+pub struct AuthnClient {
+    client: Client,
+}
+
+impl AuthnClient {
+    pub fn sessions(&self) -> SessionsClient {
+        SessionsClient::from_client(&self.client)
+    }
+
+    pub fn service_accounts(&self) -> ServiceAccountsClient {
+        ServiceAccountsClient::from_client(&self.client)
+    }
+
+    pub fn users(&self) -> UsersClient {
+        UsersClient::from_client(&self.client)
+    }
+}
+
+// AuthzClient groups the clients for authorization related resources.
+#[derive(Clone)]
+pub struct AuthzClient {
+    client: Client,
+}
+
+impl AuthzClient {
+    pub fn role_assignments(&self) -> RoleAssignmentsClient {
+        RoleAssignmentsClient::from_client(&self.client)
+    }
+
+    pub fn project_role_assignments(&self) -> ProjectRoleAssignmentsClient {
+        ProjectRoleAssignmentsClient::from_client(&self.client)
+    }
+}
+
+// CoreClient groups the clients for Brigade's core resources.
+#[derive(Clone)]
+pub struct CoreClient {
+    client: Client,
+}
+
+impl CoreClient {
+    pub fn projects(&self) -> ProjectsClient {
+        ProjectsClient::from_client(&self.client)
+    }
+
+    pub fn events(&self) -> EventsClient {
+        EventsClient::from_client(&self.client)
+    }
+
+    pub fn secrets(&self) -> SecretsClient {
+        SecretsClient::from_client(&self.client)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::FakeServer;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_sub_clients() {
+        let bc = BrigadeClient::new(
+            "https://localhost:8080".to_string(),
+            ClientConfig::new(),
+            Some("token".to_string()),
+        )
+        .unwrap();
+
+        let pc = bc.projects();
+        assert_eq!(pc.client.url_path, "projects");
+        assert_eq!(pc.client.base_address, "https://localhost:8080");
+        let token = pc.client.credentials.unwrap().token().await.unwrap();
+        assert_eq!(token, Some("token".to_string()));
+        assert_eq!(bc.core().events().client.url_path, "events");
+    }
+
+    #[tokio::test]
+    async fn test_from_vars() {
+        let server = FakeServer::start().await;
+        let mut vars = HashMap::new();
+        let from_vars =
+            |vars: &HashMap<&str, String>| BrigadeClient::from_vars(|var| vars.get(var).cloned());
+        assert!(matches!(from_vars(&vars), Err(BrigadeError::Config { .. })));
+
+        vars.insert(config::SERVER_ENV_VAR, server.address());
+        vars.insert(config::TOKEN_ENV_VAR, server.root_token());
+        vars.insert(config::INSECURE_ENV_VAR, "maybe".to_string());
+        assert!(from_vars(&vars).is_err());
+
+        vars.insert(config::INSECURE_ENV_VAR, "true".to_string());
+        let bc = from_vars(&vars).unwrap();
+        assert!(bc.client.config.allow_insecure_connections);
+        bc.projects().list(None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_from_brig_config_file() {
+        let server = FakeServer::start().await;
+        let path = std::env::temp_dir().join(format!("brig-config-sdk-{}", std::process::id()));
+        let brig = BrigConfig {
+            api_address: server.address(),
+            api_token: server.root_token(),
+            ignore_cert_errors: false,
+        };
+        std::fs::write(&path, serde_json::to_string(&brig).unwrap()).unwrap();
+        let bc = BrigadeClient::from_brig_config_file(&path).unwrap();
+        bc.projects().list(None, None).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,626 @@
+use crate::{
+    client::{Client, ClientConfig},
+    error::{BrigadeError, Result},
+    meta::{List, ListOptions},
+    pagination::{self, StreamOptions},
+};
+use futures::{stream::BoxStream, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_with::*;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    path::Path,
+    time::Duration,
+};
+
+// Secret is a key/value pair stored with a project, and exposed to its
+// workers and jobs. The API server never returns secret values, only their
+// keys.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Secret {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl Secret {
+    pub fn new(key: String, value: String) -> Self {
+        Self {
+            key,
+            value: Some(value),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SecretsClient {
+    client: Client,
+}
+
+impl SecretsClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, "projects".to_string(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("projects"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    // Lists the secrets of a project. Values are redacted by the API server.
+    pub async fn list(
+        &self,
+        project_id: String,
+        opts: Option<ListOptions>,
+    ) -> Result<List<Secret>> {
+        let url = format!(
+            "{}/v2/{}/{}/secrets",
+            self.client.base_address, self.client.url_path, project_id
+        );
+        self.client
+            .send_json(self.client.req(Method::GET, &url, opts))
+            .await
+    }
+
+    // Returns a stream over all secrets of a project, fetching pages lazily
+    // as the stream is consumed.
+    pub fn list_all(
+        &self,
+        project_id: String,
+        opts: Option<ListOptions>,
+    ) -> BoxStream<'static, Result<Secret>> {
+        let sc = self.clone();
+        let pages = pagination::pages(opts, move |opts| {
+            let sc = sc.clone();
+            let project_id = project_id.clone();
+            async move { sc.list(project_id, Some(opts)).await }
+        });
+        pagination::items(pages, StreamOptions::default())
+    }
+
+    // Sets a secret on a project, overwriting any existing value for the
+    // same key.
+    pub async fn set(&self, project_id: String, secret: &Secret) -> Result<()> {
+        let url = self.secret_url(&project_id, &secret.key)?;
+        self.client
+            .send(self.client.req(Method::PUT, &url, None).json(secret))
+            .await?;
+        Ok(())
+    }
+
+    // Removes a secret from a project. Unsetting a key that isn't set is not
+    // an error.
+    pub async fn unset(&self, project_id: String, key: String) -> Result<()> {
+        let url = self.secret_url(&project_id, &key)?;
+        self.client
+            .send(self.client.req(Method::DELETE, &url, None))
+            .await?;
+        Ok(())
+    }
+
+    // Sets every secret in `secrets` on a project, and returns how the
+    // secrets of the project changed. Existing secrets that are not in
+    // `secrets` are left alone, unless `opts.unset_missing` is set. With
+    // `opts.dry_run`, nothing is changed and the returned diff shows what
+    // would have been.
+    //
+    // The API server doesn't return secret values, so every key that is
+    // already set counts as overwritten, even if its value is the same.
+    pub async fn import(
+        &self,
+        project_id: String,
+        secrets: &BTreeMap<String, String>,
+        opts: &ImportOptions,
+    ) -> Result<ImportDiff> {
+        // Fail before anything is changed.
+        for key in secrets.keys() {
+            check_key(key)?;
+        }
+        let existing: HashSet<String> = self
+            .list_all(project_id.clone(), None)
+            .map_ok(|s| s.key)
+            .try_collect()
+            .await?;
+
+        let mut diff = ImportDiff::default();
+        for key in secrets.keys() {
+            match existing.contains(key) {
+                true => diff.overwritten.push(key.clone()),
+                false => diff.added.push(key.clone()),
+            }
+        }
+        for key in existing {
+            if secrets.contains_key(&key) {
+                continue;
+            }
+            match opts.unset_missing {
+                true => diff.unset.push(key),
+                false => diff.left_alone.push(key),
+            }
+        }
+        diff.unset.sort();
+        diff.left_alone.sort();
+        if opts.dry_run {
+            return Ok(diff);
+        }
+
+        for (key, value) in secrets.iter() {
+            let secret = Secret::new(key.clone(), value.clone());
+            self.set(project_id.clone(), &secret).await?;
+        }
+        for key in diff.unset.iter() {
+            self.unset(project_id.clone(), key.clone()).await?;
+        }
+        Ok(diff)
+    }
+
+    fn secret_url(&self, project_id: &str, key: &str) -> Result<String> {
+        check_key(key)?;
+        Ok(format!(
+            "{}/v2/{}/{}/secrets/{}",
+            self.client.base_address,
+            self.client.url_path,
+            project_id,
+            utf8_percent_encode(key, PATH_SEGMENT)
+        ))
+    }
+}
+
+// Escaping can't help with keys that are dot segments: URLs are normalized
+// before they are sent, so `..` would address the project itself.
+fn check_key(key: &str) -> Result<()> {
+    match key {
+        "" | "." | ".." => Err(BrigadeError::BadRequest {
+            reason: format!("invalid secret key {:?}", key),
+            details: Vec::new(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Characters that can't appear as is in a path segment. Keys are free form,
+// so they may contain any of these.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+// ImportOptions controls how SecretsClient::import applies secrets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportOptions {
+    // Computes the diff without changing any secret.
+    pub dry_run: bool,
+    // Unsets the secrets of the project that are not being imported, so the
+    // imported secrets become the only ones set.
+    pub unset_missing: bool,
+}
+
+// ImportDiff lists, by key, how an import changes the secrets of a project.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportDiff {
+    pub added: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub unset: Vec<String>,
+    pub left_alone: Vec<String>,
+}
+
+impl fmt::Display for ImportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for key in self.added.iter() {
+            writeln!(f, "+ {}", key)?;
+        }
+        for key in self.overwritten.iter() {
+            writeln!(f, "~ {}", key)?;
+        }
+        for key in self.unset.iter() {
+            writeln!(f, "- {}", key)?;
+        }
+        for key in self.left_alone.iter() {
+            writeln!(f, "  {}", key)?;
+        }
+        Ok(())
+    }
+}
+
+// Reads secrets from a file, picking the format from its extension:
+// `.yaml` and `.yml` files are read as YAML, `.json` files as JSON, and
+// anything else, such as `.env` or `.env.production`, as a dotenv file.
+pub fn read_secrets_file<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, String>> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+    let secrets = match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml") | Some("yml") => parse_yaml(&contents),
+        Some("json") => parse_json(&contents),
+        _ => parse_env(&contents),
+    };
+    secrets.map_err(|e| match e {
+        BrigadeError::Parse { reason } => BrigadeError::Parse {
+            reason: format!("{}: {}", path.display(), reason),
+        },
+        e => e,
+    })
+}
+
+// Parses a dotenv file. Every line is a `KEY=value` pair, optionally
+// prefixed with `export`. Blank lines and lines starting with `#` are
+// ignored. Values can be wrapped in single quotes, which are taken
+// literally, or double quotes, in which `\n`, `\t` and `\r` are escapes
+// for newline, tab and carriage return, and a backslash before any other
+// character, such as `\"` or `\\`, stands for that character. A comment may
+// follow the closing quote. Unquoted values end at the first ` #`.
+pub fn parse_env(contents: &str) -> Result<BTreeMap<String, String>> {
+    let mut secrets = BTreeMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let err = |reason: &str| BrigadeError::Parse {
+            reason: format!("line {}: {}", i + 1, reason),
+        };
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| err("expected KEY=value"))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(err("missing key"));
+        }
+        let value = value.trim();
+        let value = if let Some(v) = value.strip_prefix('\'') {
+            let end = v
+                .find('\'')
+                .ok_or_else(|| err("unterminated single quote"))?;
+            if !is_comment(&v[end + 1..]) {
+                return Err(err("unexpected characters after the closing quote"));
+            }
+            v[..end].to_string()
+        } else if let Some(v) = value.strip_prefix('"') {
+            let end = closing_double_quote(v).ok_or_else(|| err("unterminated double quote"))?;
+            if !is_comment(&v[end + 1..]) {
+                return Err(err("unexpected characters after the closing quote"));
+            }
+            unescape(&v[..end])
+        } else {
+            match value.find(" #") {
+                Some(i) => value[..i].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        };
+        secrets.insert(key.to_string(), value);
+    }
+    Ok(secrets)
+}
+
+// Reports whether what follows a quoted value is blank or a comment.
+fn is_comment(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+// Returns the index of the first double quote that isn't escaped.
+fn closing_double_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// Parses a flat YAML mapping. Scalar values are converted to strings, but
+// nested mappings and sequences are rejected.
+pub fn parse_yaml(contents: &str) -> Result<BTreeMap<String, String>> {
+    let values = serde_yaml::from_str(contents).map_err(|e| BrigadeError::Parse {
+        reason: e.to_string(),
+    })?;
+    scalars_to_strings(values)
+}
+
+// Parses a flat JSON object, with the same rules as parse_yaml.
+pub fn parse_json(contents: &str) -> Result<BTreeMap<String, String>> {
+    let values = serde_json::from_str(contents).map_err(|e| BrigadeError::Parse {
+        reason: e.to_string(),
+    })?;
+    scalars_to_strings(values)
+}
+
+// YAML documents are read into JSON values too, so both formats share the
+// same conversion rules.
+fn scalars_to_strings(
+    values: BTreeMap<String, serde_json::Value>,
+) -> Result<BTreeMap<String, String>> {
+    let mut secrets = BTreeMap::new();
+    for (key, value) in values {
+        let value = match value {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => b.to_string(),
+            _ => {
+                return Err(BrigadeError::Parse {
+                    reason: format!("the value of {} is not a string", key),
+                })
+            }
+        };
+        secrets.insert(key, value);
+    }
+    Ok(secrets)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{projects::Project, testing::FakeServer};
+    use futures::TryStreamExt;
+
+    #[tokio::test]
+    async fn test_set_and_list_secrets() {
+        let (server, sc) = get_secrets_client().await;
+        sc.set(
+            "hello-world".to_string(),
+            &Secret::new("foo".to_string(), "bar".to_string()),
+        )
+        .await
+        .unwrap();
+        sc.set(
+            "hello-world".to_string(),
+            &Secret::new("baz".to_string(), "qux".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(server.secrets("hello-world")["foo"], "bar");
+
+        let sl = sc.list("hello-world".to_string(), None).await.unwrap();
+        let secrets = sl.items.unwrap();
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(secrets[0].key, "baz");
+        assert_ne!(secrets[0].value, Some("qux".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_list_secrets_paginated() {
+        let (_server, sc) = get_secrets_client().await;
+        for i in 0..5 {
+            let secret = Secret::new(format!("key-{}", i), "value".to_string());
+            sc.set("hello-world".to_string(), &secret).await.unwrap();
+        }
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(2),
+        };
+        let sl = sc
+            .list("hello-world".to_string(), Some(opts.clone()))
+            .await
+            .unwrap();
+        assert_eq!(sl.items.unwrap().len(), 2);
+        assert_eq!(sl.metadata.remaining_item_count, Some(3));
+
+        let secrets: Vec<Secret> = sc
+            .list_all("hello-world".to_string(), Some(opts))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(secrets.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_unset_secret() {
+        let (server, sc) = get_secrets_client().await;
+        let secret = Secret::new("foo".to_string(), "bar".to_string());
+        sc.set("hello-world".to_string(), &secret).await.unwrap();
+        sc.unset("hello-world".to_string(), "foo".to_string())
+            .await
+            .unwrap();
+        assert!(server.secrets("hello-world").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_secret_key_is_escaped() {
+        let (server, sc) = get_secrets_client().await;
+        let secret = Secret::new("a/b c?d#e%f".to_string(), "bar".to_string());
+        sc.set("hello-world".to_string(), &secret).await.unwrap();
+        assert_eq!(server.secrets("hello-world")["a/b c?d#e%f"], "bar");
+
+        sc.unset("hello-world".to_string(), "a/b c?d#e%f".to_string())
+            .await
+            .unwrap();
+        assert!(server.secrets("hello-world").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dot_segment_keys_are_rejected() {
+        let (server, sc) = get_secrets_client().await;
+        for key in ["", ".", ".."].iter() {
+            let secret = Secret::new(key.to_string(), "bar".to_string());
+            let err = sc
+                .set("hello-world".to_string(), &secret)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, BrigadeError::BadRequest { .. }));
+            let err = sc
+                .unset("hello-world".to_string(), key.to_string())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, BrigadeError::BadRequest { .. }));
+        }
+        assert_eq!(server.projects().len(), 1);
+
+        let secrets: BTreeMap<String, String> = vec![
+            ("FOO".to_string(), "bar".to_string()),
+            ("..".to_string(), "bar".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let err = sc
+            .import(
+                "hello-world".to_string(),
+                &secrets,
+                &ImportOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BrigadeError::BadRequest { .. }));
+        assert!(server.secrets("hello-world").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_secrets_project_not_found() {
+        let (_server, sc) = get_secrets_client().await;
+        let err = sc.list("nope".to_string(), None).await.unwrap_err();
+        assert!(err.is_not_found());
+        let secret = Secret::new("foo".to_string(), "bar".to_string());
+        let err = sc.set("nope".to_string(), &secret).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let secrets = parse_env(
+            r#"
+# A comment
+FOO=bar
+export BAZ = qux # trailing comment
+SINGLE='a # literal\n'
+DOUBLE="line one\nline \"two\""
+EMPTY=
+COMMENTED='value' # a comment
+ESCAPED="a \\" # b"
+OTHER="a\rb\qc"
+"#,
+        )
+        .unwrap();
+        assert_eq!(secrets.len(), 8);
+        assert_eq!(secrets["OTHER"], "a\rbqc");
+        assert_eq!(secrets["COMMENTED"], "value");
+        assert_eq!(secrets["ESCAPED"], "a \\");
+        assert_eq!(secrets["FOO"], "bar");
+        assert_eq!(secrets["BAZ"], "qux");
+        assert_eq!(secrets["SINGLE"], "a # literal\\n");
+        assert_eq!(secrets["DOUBLE"], "line one\nline \"two\"");
+        assert_eq!(secrets["EMPTY"], "");
+
+        match parse_env("FOO=bar\nnope").unwrap_err() {
+            BrigadeError::Parse { reason } => assert!(reason.starts_with("line 2")),
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert!(parse_env("FOO=\"bar").is_err());
+        assert!(parse_env("FOO='bar' baz").is_err());
+    }
+
+    #[test]
+    fn test_parse_yaml_and_json() {
+        let secrets = parse_yaml("foo: bar\nport: 8080\ndebug: true\n").unwrap();
+        assert_eq!(secrets["foo"], "bar");
+        assert_eq!(secrets["port"], "8080");
+        assert_eq!(secrets["debug"], "true");
+        assert!(parse_yaml("foo:\n  bar: baz\n").is_err());
+
+        let secrets = parse_json(r#"{"foo": "bar", "port": 8080}"#).unwrap();
+        assert_eq!(secrets["port"], "8080");
+        assert!(parse_json(r#"{"foo": ["bar"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_read_secrets_file_errors() {
+        let path =
+            std::env::temp_dir().join(format!("brigade-secrets-{}.json", std::process::id()));
+        fs::write(&path, "{not json").unwrap();
+        let err = read_secrets_file(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        match err {
+            BrigadeError::Parse { reason } => {
+                assert!(reason.starts_with(&path.display().to_string()))
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_secrets() {
+        let (server, sc) = get_secrets_client().await;
+        for key in ["FOO", "OLD"].iter() {
+            let secret = Secret::new(key.to_string(), "old".to_string());
+            sc.set("hello-world".to_string(), &secret).await.unwrap();
+        }
+        let secrets = parse_env("FOO=new\nBAR=new\n").unwrap();
+
+        let opts = ImportOptions {
+            dry_run: true,
+            unset_missing: false,
+        };
+        let diff = sc
+            .import("hello-world".to_string(), &secrets, &opts)
+            .await
+            .unwrap();
+        assert_eq!(diff.added, vec!["BAR"]);
+        assert_eq!(diff.overwritten, vec!["FOO"]);
+        assert_eq!(diff.left_alone, vec!["OLD"]);
+        assert!(diff.unset.is_empty());
+        assert_eq!(diff.to_string(), "+ BAR\n~ FOO\n  OLD\n");
+        assert_eq!(server.secrets("hello-world")["FOO"], "old");
+
+        let opts = ImportOptions {
+            dry_run: false,
+            unset_missing: true,
+        };
+        let diff = sc
+            .import("hello-world".to_string(), &secrets, &opts)
+            .await
+            .unwrap();
+        assert_eq!(diff.unset, vec!["OLD"]);
+        assert_eq!(server.secrets("hello-world"), secrets);
+    }
+
+    async fn get_secrets_client() -> (FakeServer, SecretsClient) {
+        let server = FakeServer::start().await;
+        server.add_project(Project::new(
+            "hello-world".to_string(),
+            "A test project".to_string(),
+            "console.log('hello')".to_string(),
+        ));
+        let sc = server.brigade_client().secrets();
+        (server, sc)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    authn::Token,
+    client::{Client, ClientConfig},
+    error::Result,
+    meta::{APIVersion, Kind, List, ListOptions, ObjectMeta, TypeMeta},
+    pagination::{self, StreamOptions},
+};
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_with::*;
+use std::time::Duration;
+
+// ServiceAccount is a non-human principal, such as a gateway, that
+// authenticates with a non-expiring token.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccount {
+    pub metadata: ObjectMeta,
+    #[serde(flatten)]
+    pub type_meta: Option<TypeMeta>,
+
+    pub description: String,
+    // Set when the service account is locked. A locked service account can't
+    // authenticate.
+    pub locked: Option<DateTime<Utc>>,
+}
+
+impl ServiceAccount {
+    pub fn new(id: String, description: String) -> Self {
+        Self {
+            metadata: ObjectMeta { id, created: None },
+            type_meta: Some(TypeMeta {
+                kind: Kind::ServiceAccount,
+                api_version: APIVersion::V2,
+            }),
+            description,
+            locked: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ServiceAccountsClient {
+    client: Client,
+}
+
+impl ServiceAccountsClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, "service-accounts".to_string(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("service-accounts"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    // Creates a service account, returning its token. The API server only
+    // returns the token once, so it should be stored right away.
+    pub async fn create(&self, service_account: &ServiceAccount) -> Result<Token> {
+        let mut service_account = service_account.clone();
+        self.ensure_service_account_meta(&mut service_account);
+        self.client.create(&service_account).await
+    }
+
+    pub async fn get(&self, id: String) -> Result<ServiceAccount> {
+        self.client.get(id).await
+    }
+
+    pub async fn list(&self, opts: Option<ListOptions>) -> Result<List<ServiceAccount>> {
+        self.client.list(opts).await
+    }
+
+    // Returns a stream over all service accounts, fetching pages lazily as
+    // the stream is consumed.
+    pub fn list_all(
+        &self,
+        opts: Option<ListOptions>,
+    ) -> BoxStream<'static, Result<ServiceAccount>> {
+        let sac = self.clone();
+        let pages = pagination::pages(opts, move |opts| {
+            let sac = sac.clone();
+            async move { sac.list(Some(opts)).await }
+        });
+        pagination::items(pages, StreamOptions::default())
+    }
+
+    // Locks a service account, revoking its token.
+    pub async fn lock(&self, id: String) -> Result<()> {
+        let url = self.lock_url(&id);
+        self.client
+            .send(self.client.req(Method::PUT, &url, None))
+            .await?;
+        Ok(())
+    }
+
+    // Unlocks a service account, returning a new token for it. The token
+    // revoked when it was locked stays invalid.
+    pub async fn unlock(&self, id: String) -> Result<Token> {
+        let url = self.lock_url(&id);
+        self.client
+            .send_json(self.client.req(Method::DELETE, &url, None))
+            .await
+    }
+
+    pub async fn delete(&self, id: String) -> Result<()> {
+        self.client.delete::<ServiceAccount>(id).await
+    }
+
+    fn lock_url(&self, id: &str) -> String {
+        format!(
+            "{}/v2/{}/{}/lock",
+            self.client.base_address, self.client.url_path, id
+        )
+    }
+
+    fn ensure_service_account_meta(&self, service_account: &mut ServiceAccount) {
+        service_account.type_meta = Some(TypeMeta {
+            kind: Kind::ServiceAccount,
+            api_version: APIVersion::V2,
+        });
+
+        // These fields are set by the server.
+        service_account.metadata.created = None;
+        service_account.locked = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::BrigadeError, testing::FakeServer};
+
+    #[tokio::test]
+    async fn test_create_and_get_service_account() {
+        let server = FakeServer::start().await;
+        let sac = server.brigade_client().authn().service_accounts();
+        let token = sac.create(&new_service_account()).await.unwrap();
+        assert_eq!(token.type_meta.kind, Kind::Token);
+        assert!(!token.value.is_empty());
+
+        let sa = sac.get("gateway".to_string()).await.unwrap();
+        assert_eq!(sa.description, "A gateway");
+        assert!(sa.metadata.created.is_some());
+        assert!(sa.locked.is_none());
+
+        let err = sac.create(&new_service_account()).await.unwrap_err();
+        assert!(err.is_conflict());
+    }
+
+    #[tokio::test]
+    async fn test_list_service_accounts() {
+        let server = FakeServer::start().await;
+        let sac = server.brigade_client().authn().service_accounts();
+        for i in 0..3 {
+            let sa = ServiceAccount::new(format!("gateway-{}", i), "A gateway".to_string());
+            sac.create(&sa).await.unwrap();
+        }
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(2),
+        };
+        let sal = sac.list(Some(opts)).await.unwrap();
+        assert_eq!(sal.items.unwrap().len(), 2);
+        assert_eq!(sal.metadata.remaining_item_count, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_unlock_service_account() {
+        let server = FakeServer::start().await;
+        let bc = server.brigade_client();
+        let sac = bc.authn().service_accounts();
+        let token = sac.create(&new_service_account()).await.unwrap();
+        let sa_client = ServiceAccountsClient::new(
+            server.address(),
+            ClientConfig::new(),
+            Some(token.value.clone()),
+        )
+        .unwrap();
+        sa_client.get("gateway".to_string()).await.unwrap();
+
+        sac.lock("gateway".to_string()).await.unwrap();
+        let sa = sac.get("gateway".to_string()).await.unwrap();
+        assert!(sa.locked.is_some());
+        let err = sa_client.get("gateway".to_string()).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+
+        let new_token = sac.unlock("gateway".to_string()).await.unwrap();
+        assert_ne!(new_token.value, token.value);
+        let sa = sac.get("gateway".to_string()).await.unwrap();
+        assert!(sa.locked.is_none());
+        let err = sa_client.get("gateway".to_string()).await.unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_delete_service_account() {
+        let server = FakeServer::start().await;
+        let sac = server.brigade_client().authn().service_accounts();
+        sac.create(&new_service_account()).await.unwrap();
+        sac.delete("gateway".to_string()).await.unwrap();
+        let err = sac.get("gateway".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
+        let err = sac.delete("gateway".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    fn new_service_account() -> ServiceAccount {
+        ServiceAccount::new("gateway".to_string(), "A gateway".to_string())
+    }
+}
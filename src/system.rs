@@ -0,0 +1,68 @@
+use crate::{
+    client::{Client, ClientConfig},
+    error::Result,
+    meta::TypeMeta,
+};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_with::*;
+use std::time::Duration;
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PingResponse {
+    #[serde(flatten)]
+    pub type_meta: Option<TypeMeta>,
+
+    pub version: Option<String>,
+}
+
+pub struct SystemClient {
+    client: Client,
+}
+
+impl SystemClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, "ping".to_string(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("ping"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    // Pings the API server, returning the version it runs.
+    pub async fn ping(&self) -> Result<PingResponse> {
+        let url = format!("{}/v2/{}", self.client.base_address, self.client.url_path);
+        self.client
+            .send_json(self.client.req(Method::GET, &url, None))
+            .await
+    }
+
+    // Pings the API server without going through a versioned endpoint.
+    // This is useful for checking whether the server is up at all.
+    pub async fn unversioned_ping(&self) -> Result<String> {
+        let url = format!("{}/{}", self.client.base_address, self.client.url_path);
+        self.client
+            .send_text(self.client.req(Method::GET, &url, None))
+            .await
+    }
+}
+
+#[test]
+fn test_ping_response_deserialization() {
+    let res: PingResponse = serde_json::from_str(
+        r#"{"kind":"PingResponse","apiVersion":"brigade.sh/v2","version":"v2.0.0"}"#,
+    )
+    .unwrap();
+    assert_eq!(res.version, Some("v2.0.0".to_string()));
+}
@@ -0,0 +1,977 @@
+// An in-process fake of the Brigade API server, for tests that should not
+// depend on a real Brigade installation.
+//
+// FakeServer keeps projects, events, secrets and sessions in memory, and
+// implements enough of the v2 API for the clients in this crate: CRUD for
+// projects, creating events (fanning them out to subscribed projects),
+// listing with `continue`/`limit` pagination, root and user sessions,
+// service accounts, users and system and project role assignments.
+//
+// let server = FakeServer::start().await;
+// let bc = server.brigade_client();
+// bc.projects().create(&project).await?;
+
+use crate::{
+    authn::{
+        PrincipalReference, PrincipalType, Token, UserSessionAuthDetails, UserSessionCreateOptions,
+    },
+    authz::{ProjectRoleAssignment, RoleAssignment},
+    client::ClientConfig,
+    events::{CancelManyEventsResult, DeleteManyEventsResult, Event},
+    meta::{APIVersion, Kind, List, ListMeta, ObjectMeta, TypeMeta},
+    projects::Project,
+    sdk::BrigadeClient,
+    service_accounts::ServiceAccount,
+    system::PingResponse,
+    users::User,
+    worker::{Worker, WorkerPhase, WorkerSpec, WorkerStatus},
+};
+use chrono::Utc;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use percent_encoding::percent_decode_str;
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+pub const ROOT_PASSWORD: &str = "F00Bar!!!";
+
+const REDACTED: &str = "*** REDACTED ***";
+
+pub struct FakeServer {
+    address: String,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct State {
+    projects: BTreeMap<String, Project>,
+    // Events are kept in the order they were created in.
+    events: Vec<Event>,
+    secrets: HashMap<String, BTreeMap<String, String>>,
+    service_accounts: BTreeMap<String, ServiceAccount>,
+    users: BTreeMap<String, User>,
+    role_assignments: Vec<RoleAssignment>,
+    project_role_assignments: Vec<ProjectRoleAssignment>,
+    tokens: HashMap<String, Principal>,
+    // The success URLs of user sessions waiting for the user to log in, by
+    // token.
+    user_sessions: HashMap<String, Option<String>>,
+}
+
+// The principal a token was issued to.
+#[derive(Clone, PartialEq)]
+enum Principal {
+    Root,
+    ServiceAccount(String),
+    User(String),
+}
+
+impl FakeServer {
+    // Starts the server on a random local port. It stops when dropped.
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let svc_state = state.clone();
+        let make_svc = make_service_fn(move |_| {
+            let state = svc_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let server = Server::bind(&addr).serve(make_svc);
+        let address = format!("http://{}", server.local_addr());
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            rx.await.ok();
+        }));
+        Self {
+            address,
+            state,
+            shutdown: Some(tx),
+        }
+    }
+
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    // Returns a new token for the root user.
+    pub fn root_token(&self) -> String {
+        issue_token(&mut self.state.lock().unwrap(), Principal::Root).value
+    }
+
+    // Returns a client authenticated as root.
+    pub fn brigade_client(&self) -> BrigadeClient {
+        BrigadeClient::new(self.address(), ClientConfig::new(), Some(self.root_token())).unwrap()
+    }
+
+    // Returns a new token for a user, as if they had logged in.
+    pub fn user_token(&self, id: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        issue_token(&mut state, Principal::User(id.to_string())).value
+    }
+
+    // Completes the login of a user session, as the identity provider would,
+    // creating the user if needed. Returns the success URL given when the
+    // session was created, which the user's browser would be redirected to.
+    pub fn authenticate_user_session(&self, token: &str, user_id: &str) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        let success_url = state.user_sessions.remove(token).flatten();
+        state
+            .tokens
+            .insert(token.to_string(), Principal::User(user_id.to_string()));
+        state.users.entry(user_id.to_string()).or_insert(User {
+            metadata: ObjectMeta {
+                id: user_id.to_string(),
+                created: Some(Utc::now()),
+            },
+            type_meta: Some(type_meta(Kind::User)),
+            name: user_id.to_string(),
+            locked: None,
+        });
+        success_url
+    }
+
+    // Stores a user as is, as if they had logged in for the first time.
+    pub fn add_user(&self, mut user: User) {
+        user.metadata.created.get_or_insert_with(Utc::now);
+        let mut state = self.state.lock().unwrap();
+        state.users.insert(user.metadata.id.clone(), user);
+    }
+
+    // Stores a project as is, without any validation.
+    pub fn add_project(&self, project: Project) {
+        let mut state = self.state.lock().unwrap();
+        state.projects.insert(project.metadata.id.clone(), project);
+    }
+
+    // Stores an event as is, without any validation.
+    pub fn add_event(&self, event: Event) {
+        self.state.lock().unwrap().events.push(event);
+    }
+
+    pub fn projects(&self) -> Vec<Project> {
+        let state = self.state.lock().unwrap();
+        state.projects.values().cloned().collect()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.state.lock().unwrap().events.clone()
+    }
+
+    pub fn secrets(&self, project_id: &str) -> BTreeMap<String, String> {
+        let state = self.state.lock().unwrap();
+        state.secrets.get(project_id).cloned().unwrap_or_default()
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            tx.send(()).ok();
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = req.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+    let query: HashMap<String, String> = parts
+        .uri
+        .query()
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let segments: Vec<String> = parts
+        .uri
+        .path()
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+        .collect();
+    let path: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    let mut state = state.lock().unwrap();
+
+    // Unauthenticated endpoints.
+    match (&parts.method, path.as_slice()) {
+        (&Method::GET, ["ping"]) => return Ok(Response::new(Body::from("OK"))),
+        (&Method::GET, ["v2", "ping"]) => {
+            let res = PingResponse {
+                type_meta: Some(type_meta(Kind::PingResponse)),
+                version: Some("v2.0.0-fake".to_string()),
+            };
+            return Ok(json_response(StatusCode::OK, &res));
+        }
+        (&Method::POST, ["v2", "sessions"]) => {
+            return Ok(create_session(&mut state, &parts.headers, &query, &body));
+        }
+        _ => {}
+    }
+
+    let principal = match principal(&state, &parts.headers) {
+        Some(principal) => principal,
+        None => {
+            return Ok(error(
+                StatusCode::UNAUTHORIZED,
+                json!({"kind": "AuthenticationError", "reason": "Could not authenticate the request"}),
+            ))
+        }
+    };
+
+    let res = match (&parts.method, path.as_slice()) {
+        (&Method::GET, ["v2", "whoami"]) => {
+            let res = match principal {
+                Principal::Root => PrincipalReference {
+                    principal_type: PrincipalType::Root,
+                    id: "root".to_string(),
+                },
+                Principal::ServiceAccount(id) => PrincipalReference {
+                    principal_type: PrincipalType::ServiceAccount,
+                    id,
+                },
+                Principal::User(id) => PrincipalReference {
+                    principal_type: PrincipalType::User,
+                    id,
+                },
+            };
+            json_response(StatusCode::OK, &res)
+        }
+        (&Method::DELETE, ["v2", "session"]) => {
+            if let Some(token) = bearer_token(&parts.headers) {
+                state.tokens.remove(token);
+            }
+            empty(StatusCode::OK)
+        }
+        (&Method::GET, ["v2", "projects"]) => {
+            let projects: Vec<Project> = state.projects.values().cloned().collect();
+            list_response(projects, &query)
+        }
+        (&Method::POST, ["v2", "projects"]) => match parse::<Project>(&body) {
+            Ok(mut project) => match state.projects.entry(project.metadata.id.clone()) {
+                Entry::Occupied(e) => conflict("Project", e.key()),
+                Entry::Vacant(e) => {
+                    project.metadata.created = Some(Utc::now());
+                    e.insert(project.clone());
+                    json_response(StatusCode::CREATED, &project)
+                }
+            },
+            Err(e) => bad_request(e),
+        },
+        (&Method::GET, ["v2", "projects", id]) => match state.projects.get(*id) {
+            Some(project) => json_response(StatusCode::OK, project),
+            None => not_found("Project", id),
+        },
+        (&Method::PUT, ["v2", "projects", id]) => match parse::<Project>(&body) {
+            Ok(mut project) => match state.projects.get(*id) {
+                Some(existing) => {
+                    project.metadata = existing.metadata.clone();
+                    state.projects.insert(id.to_string(), project.clone());
+                    json_response(StatusCode::OK, &project)
+                }
+                None => not_found("Project", id),
+            },
+            Err(e) => bad_request(e),
+        },
+        (&Method::DELETE, ["v2", "projects", id]) => {
+            if state.projects.remove(*id).is_some() {
+                state.events.retain(|e| e.project_id != *id);
+                state.secrets.remove(*id);
+                state
+                    .project_role_assignments
+                    .retain(|ra| ra.project_id != *id);
+                empty(StatusCode::OK)
+            } else {
+                not_found("Project", id)
+            }
+        }
+        (&Method::GET, ["v2", "projects", id, "secrets"]) => {
+            if !state.projects.contains_key(*id) {
+                not_found("Project", id)
+            } else {
+                let secrets = state
+                    .secrets
+                    .get(*id)
+                    .map(|s| {
+                        s.keys()
+                            .map(|k| json!({"key": k, "value": REDACTED}))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                list_response::<serde_json::Value>(secrets, &query)
+            }
+        }
+        (&Method::PUT, ["v2", "projects", id, "secrets", key]) => {
+            match parse::<serde_json::Value>(&body) {
+                Ok(secret) => {
+                    if !state.projects.contains_key(*id) {
+                        not_found("Project", id)
+                    } else {
+                        let value = secret["value"].as_str().unwrap_or_default().to_string();
+                        state
+                            .secrets
+                            .entry(id.to_string())
+                            .or_default()
+                            .insert(key.to_string(), value);
+                        empty(StatusCode::OK)
+                    }
+                }
+                Err(e) => bad_request(e),
+            }
+        }
+        (&Method::DELETE, ["v2", "projects", id, "secrets", key]) => {
+            if !state.projects.contains_key(*id) {
+                not_found("Project", id)
+            } else {
+                if let Some(s) = state.secrets.get_mut(*id) {
+                    s.remove(*key);
+                }
+                empty(StatusCode::OK)
+            }
+        }
+        (&Method::GET, ["v2", "service-accounts"]) => {
+            let service_accounts: Vec<ServiceAccount> =
+                state.service_accounts.values().cloned().collect();
+            list_response(service_accounts, &query)
+        }
+        (&Method::POST, ["v2", "service-accounts"]) => match parse::<ServiceAccount>(&body) {
+            Ok(mut sa) => match state.service_accounts.entry(sa.metadata.id.clone()) {
+                Entry::Occupied(e) => conflict("ServiceAccount", e.key()),
+                Entry::Vacant(e) => {
+                    sa.metadata.created = Some(Utc::now());
+                    e.insert(sa.clone());
+                    let token = issue_token(&mut state, Principal::ServiceAccount(sa.metadata.id));
+                    json_response(StatusCode::CREATED, &token)
+                }
+            },
+            Err(e) => bad_request(e),
+        },
+        (&Method::GET, ["v2", "service-accounts", id]) => match state.service_accounts.get(*id) {
+            Some(sa) => json_response(StatusCode::OK, sa),
+            None => not_found("ServiceAccount", id),
+        },
+        (&Method::DELETE, ["v2", "service-accounts", id]) => {
+            if state.service_accounts.remove(*id).is_some() {
+                revoke_tokens(&mut state, &Principal::ServiceAccount(id.to_string()));
+                empty(StatusCode::OK)
+            } else {
+                not_found("ServiceAccount", id)
+            }
+        }
+        (&Method::PUT, ["v2", "service-accounts", id, "lock"]) => {
+            match state.service_accounts.get_mut(*id) {
+                Some(sa) => {
+                    sa.locked = Some(Utc::now());
+                    revoke_tokens(&mut state, &Principal::ServiceAccount(id.to_string()));
+                    empty(StatusCode::OK)
+                }
+                None => not_found("ServiceAccount", id),
+            }
+        }
+        (&Method::DELETE, ["v2", "service-accounts", id, "lock"]) => {
+            match state.service_accounts.get_mut(*id) {
+                Some(sa) => {
+                    sa.locked = None;
+                    let token = issue_token(&mut state, Principal::ServiceAccount(id.to_string()));
+                    json_response(StatusCode::OK, &token)
+                }
+                None => not_found("ServiceAccount", id),
+            }
+        }
+        (&Method::GET, ["v2", "users"]) => {
+            let users: Vec<User> = state.users.values().cloned().collect();
+            list_response(users, &query)
+        }
+        (&Method::GET, ["v2", "users", id]) => match state.users.get(*id) {
+            Some(user) => json_response(StatusCode::OK, user),
+            None => not_found("User", id),
+        },
+        (&Method::DELETE, ["v2", "users", id]) => {
+            if state.users.remove(*id).is_some() {
+                revoke_tokens(&mut state, &Principal::User(id.to_string()));
+                empty(StatusCode::OK)
+            } else {
+                not_found("User", id)
+            }
+        }
+        (&Method::PUT, ["v2", "users", id, "lock"]) => match state.users.get_mut(*id) {
+            Some(user) => {
+                user.locked = Some(Utc::now());
+                revoke_tokens(&mut state, &Principal::User(id.to_string()));
+                empty(StatusCode::OK)
+            }
+            None => not_found("User", id),
+        },
+        (&Method::DELETE, ["v2", "users", id, "lock"]) => match state.users.get_mut(*id) {
+            Some(user) => {
+                user.locked = None;
+                empty(StatusCode::OK)
+            }
+            None => not_found("User", id),
+        },
+        (&Method::GET, ["v2", "projects", id, "role-assignments"]) => {
+            if !state.projects.contains_key(*id) {
+                not_found("Project", id)
+            } else {
+                let role_assignments: Vec<ProjectRoleAssignment> = state
+                    .project_role_assignments
+                    .iter()
+                    .filter(|ra| ra.project_id == *id)
+                    .filter(|ra| matches_query(ra.role, &ra.principal, &query))
+                    .cloned()
+                    .collect();
+                list_response(role_assignments, &query)
+            }
+        }
+        (&Method::POST, ["v2", "projects", id, "role-assignments"]) => {
+            match parse::<ProjectRoleAssignment>(&body) {
+                Ok(mut ra) => {
+                    ra.project_id = id.to_string();
+                    if !state.projects.contains_key(*id) {
+                        not_found("Project", id)
+                    } else if !principal_exists(&state, &ra.principal) {
+                        not_found(principal_kind(&ra.principal), &ra.principal.id)
+                    } else {
+                        if !state.project_role_assignments.contains(&ra) {
+                            state.project_role_assignments.push(ra);
+                        }
+                        empty(StatusCode::OK)
+                    }
+                }
+                Err(e) => bad_request(e),
+            }
+        }
+        (&Method::DELETE, ["v2", "projects", id, "role-assignments"]) => {
+            if !state.projects.contains_key(*id) {
+                not_found("Project", id)
+            } else {
+                state.project_role_assignments.retain(|ra| {
+                    !(ra.project_id == *id && matches_query(ra.role, &ra.principal, &query))
+                });
+                empty(StatusCode::OK)
+            }
+        }
+        (&Method::GET, ["v2", "role-assignments"]) => {
+            let role_assignments: Vec<RoleAssignment> = state
+                .role_assignments
+                .iter()
+                .filter(|ra| matches_query(ra.role, &ra.principal, &query))
+                .cloned()
+                .collect();
+            list_response(role_assignments, &query)
+        }
+        (&Method::POST, ["v2", "role-assignments"]) => match parse::<RoleAssignment>(&body) {
+            Ok(ra) => {
+                if !principal_exists(&state, &ra.principal) {
+                    not_found(principal_kind(&ra.principal), &ra.principal.id)
+                } else {
+                    if !state.role_assignments.contains(&ra) {
+                        state.role_assignments.push(ra);
+                    }
+                    empty(StatusCode::OK)
+                }
+            }
+            Err(e) => bad_request(e),
+        },
+        (&Method::DELETE, ["v2", "role-assignments"]) => {
+            state.role_assignments.retain(|ra| {
+                !(matches_query(ra.role, &ra.principal, &query)
+                    && ra.scope.as_deref() == query.get("scope").map(|s| s.as_str()))
+            });
+            empty(StatusCode::OK)
+        }
+        (&Method::GET, ["v2", "events"]) => {
+            let phases = worker_phases(&query);
+            // Like the API server, list the most recent events first.
+            let events: Vec<Event> = state
+                .events
+                .iter()
+                .rev()
+                .filter(|e| match query.get("projectID") {
+                    Some(id) => e.project_id == *id,
+                    None => true,
+                })
+                .filter(|e| phases.is_empty() || phases.contains(&phase(e)))
+                .filter(|e| query.get("source").map(|s| e.source == *s).unwrap_or(true))
+                .filter(|e| {
+                    query
+                        .get("type")
+                        .map(|t| e.event_type == *t)
+                        .unwrap_or(true)
+                })
+                .filter(|e| has_qualifiers(e.qualifiers.as_ref(), query.get("qualifiers")))
+                .filter(|e| has_labels(e.labels.as_ref(), query.get("labels")))
+                .filter(|e| {
+                    let state = e.source_state.as_ref().and_then(|s| s.state.as_ref());
+                    has_labels(state, query.get("sourceState"))
+                })
+                .cloned()
+                .collect();
+            list_response(events, &query)
+        }
+        (&Method::POST, ["v2", "events", "cancellations"]) => match many_events(&query) {
+            Some((project_id, phases)) => {
+                let mut count = 0;
+                for event in state.events.iter_mut() {
+                    if event.project_id == project_id
+                        && phases.contains(&phase(event))
+                        && cancel(event)
+                    {
+                        count += 1;
+                    }
+                }
+                json_response(StatusCode::OK, &CancelManyEventsResult { count })
+            }
+            None => missing_many_events_criteria(),
+        },
+        (&Method::DELETE, ["v2", "events"]) => match many_events(&query) {
+            Some((project_id, phases)) => {
+                let before = state.events.len();
+                state
+                    .events
+                    .retain(|e| e.project_id != project_id || !phases.contains(&phase(e)));
+                let count = (before - state.events.len()) as i64;
+                json_response(StatusCode::OK, &DeleteManyEventsResult { count })
+            }
+            None => missing_many_events_criteria(),
+        },
+        (&Method::POST, ["v2", "events"]) => match parse::<Event>(&body) {
+            Ok(event) => create_events(&mut state, event),
+            Err(e) => bad_request(e),
+        },
+        (&Method::GET, ["v2", "events", id]) => match state.events.iter().find(|e| has_id(e, id)) {
+            Some(event) => json_response(StatusCode::OK, event),
+            None => not_found("Event", id),
+        },
+        (&Method::DELETE, ["v2", "events", id]) => {
+            match state.events.iter().position(|e| has_id(e, id)) {
+                Some(i) => {
+                    state.events.remove(i);
+                    empty(StatusCode::OK)
+                }
+                None => not_found("Event", id),
+            }
+        }
+        (&Method::POST, ["v2", "events", id, "retries"]) => {
+            match state.events.iter().find(|e| has_id(e, id)).cloned() {
+                Some(event) if !phase(&event).is_terminal() => conflict("Event", id),
+                Some(event) => {
+                    let spec = event.worker.as_ref().map(|w| w.spec.clone());
+                    copy_event(&mut state, event, spec)
+                }
+                None => not_found("Event", id),
+            }
+        }
+        (&Method::POST, ["v2", "events", id, "clones"]) => {
+            match state.events.iter().find(|e| has_id(e, id)).cloned() {
+                Some(event) => copy_event(&mut state, event, None),
+                None => not_found("Event", id),
+            }
+        }
+        (&Method::PUT, ["v2", "events", id, "cancellation"]) => {
+            match state.events.iter_mut().find(|e| has_id(e, id)) {
+                Some(event) => {
+                    if cancel(event) {
+                        empty(StatusCode::OK)
+                    } else {
+                        conflict("Event", id)
+                    }
+                }
+                None => not_found("Event", id),
+            }
+        }
+        _ => error(
+            StatusCode::NOT_FOUND,
+            json!({"kind": "NotFoundError", "reason": "No such endpoint"}),
+        ),
+    };
+    Ok(res)
+}
+
+fn create_session(
+    state: &mut State,
+    headers: &hyper::HeaderMap,
+    query: &HashMap<String, String>,
+    body: &[u8],
+) -> Response<Body> {
+    if query.get("root").map(|r| r.as_str()) != Some("true") {
+        return create_user_session(state, body);
+    }
+    let expected = format!(
+        "Basic {}",
+        base64::encode(format!("root:{}", ROOT_PASSWORD))
+    );
+    let authorized = headers
+        .get(hyper::header::AUTHORIZATION)
+        .map(|h| h.as_bytes() == expected.as_bytes())
+        .unwrap_or(false);
+    if !authorized {
+        return error(
+            StatusCode::UNAUTHORIZED,
+            json!({"kind": "AuthenticationError", "reason": "Could not authenticate the request"}),
+        );
+    }
+    let token = issue_token(state, Principal::Root);
+    json_response(StatusCode::CREATED, &token)
+}
+
+// Starts a user session. The token is only usable once the session is
+// authenticated with FakeServer::authenticate_user_session.
+fn create_user_session(state: &mut State, body: &[u8]) -> Response<Body> {
+    let opts = match body.is_empty() {
+        true => UserSessionCreateOptions::default(),
+        false => match parse::<UserSessionCreateOptions>(body) {
+            Ok(opts) => opts,
+            Err(e) => return bad_request(e),
+        },
+    };
+    let details = UserSessionAuthDetails {
+        auth_url: format!("https://idp.example.com/authorize?state={}", new_id()),
+        token: new_id(),
+    };
+    state
+        .user_sessions
+        .insert(details.token.clone(), opts.success_url);
+    json_response(StatusCode::CREATED, &details)
+}
+
+fn issue_token(state: &mut State, principal: Principal) -> Token {
+    let token = Token {
+        type_meta: type_meta(Kind::Token),
+        value: new_id(),
+    };
+    state.tokens.insert(token.value.clone(), principal);
+    token
+}
+
+fn revoke_tokens(state: &mut State, principal: &Principal) {
+    state.tokens.retain(|_, p| p != principal);
+}
+
+// Returns the principal behind the bearer token of a request.
+fn principal(state: &State, headers: &hyper::HeaderMap) -> Option<Principal> {
+    bearer_token(headers)
+        .and_then(|t| state.tokens.get(t))
+        .cloned()
+}
+
+fn bearer_token(headers: &hyper::HeaderMap) -> Option<&str> {
+    headers
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+// Creates one event per target project: the project named in the event,
+// or every project subscribed to the event's source and type.
+fn create_events(state: &mut State, event: Event) -> Response<Body> {
+    let targets: Vec<Project> = if !event.project_id.is_empty() {
+        match state.projects.get(&event.project_id) {
+            Some(project) => vec![project.clone()],
+            None => return not_found("Project", &event.project_id),
+        }
+    } else {
+        state
+            .projects
+            .values()
+            .filter(|p| subscribed(p, &event))
+            .cloned()
+            .collect()
+    };
+
+    let mut created = Vec::new();
+    for project in targets {
+        let mut e = event.clone();
+        e.project_id = project.metadata.id.clone();
+        e.metadata = Some(ObjectMeta {
+            id: new_id(),
+            created: Some(Utc::now()),
+        });
+        e.worker = Some(Worker {
+            spec: project.spec.worker_template.clone(),
+            status: WorkerStatus {
+                started: None,
+                ended: None,
+                phase: Some(WorkerPhase::Pending),
+            },
+            jobs: None,
+        });
+        state.events.push(e.clone());
+        created.push(e);
+    }
+    json_response(StatusCode::CREATED, &list(created, None, None))
+}
+
+// Creates a new event from an existing one, as retries and clones do. Retries
+// keep the original worker spec; clones use the project's current one.
+fn copy_event(state: &mut State, event: Event, spec: Option<WorkerSpec>) -> Response<Body> {
+    let spec = match spec {
+        Some(spec) => spec,
+        None => match state.projects.get(&event.project_id) {
+            Some(project) => project.spec.worker_template.clone(),
+            None => return not_found("Project", &event.project_id),
+        },
+    };
+    let mut e = event;
+    e.metadata = Some(ObjectMeta {
+        id: new_id(),
+        created: Some(Utc::now()),
+    });
+    e.worker = Some(Worker {
+        spec,
+        status: WorkerStatus {
+            started: None,
+            ended: None,
+            phase: Some(WorkerPhase::Pending),
+        },
+        jobs: None,
+    });
+    state.events.push(e.clone());
+    json_response(StatusCode::CREATED, &e)
+}
+
+fn subscribed(project: &Project, event: &Event) -> bool {
+    let subs = match project.spec.event_subscriptions.as_ref() {
+        Some(subs) => subs,
+        None => return false,
+    };
+    let labels = event.labels.clone().unwrap_or_default();
+    subs.iter().any(|s| {
+        s.source == event.source
+            && s.types.iter().any(|t| t == "*" || *t == event.event_type)
+            && s.labels.iter().all(|(k, v)| labels.get(k) == Some(v))
+    })
+}
+
+// Cancels a pending or running event, returning false if it already ended.
+fn cancel(event: &mut Event) -> bool {
+    let worker = match event.worker.as_mut() {
+        Some(worker) => worker,
+        None => return false,
+    };
+    let phase = match worker.status.phase {
+        Some(WorkerPhase::Pending) => WorkerPhase::Canceled,
+        Some(WorkerPhase::Starting) | Some(WorkerPhase::Running) => WorkerPhase::Aborted,
+        _ => return false,
+    };
+    worker.status.phase = Some(phase);
+    worker.status.ended = Some(Utc::now());
+    true
+}
+
+fn phase(event: &Event) -> WorkerPhase {
+    event
+        .worker
+        .as_ref()
+        .and_then(|w| w.status.phase)
+        .unwrap_or(WorkerPhase::Unknown)
+}
+
+fn worker_phases(query: &HashMap<String, String>) -> Vec<WorkerPhase> {
+    query
+        .get("workerPhases")
+        .map(|p| {
+            p.split(',')
+                .filter_map(|p| serde_json::from_value(json!(p)).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Like the API server, bulk operations on events require a project and at
+// least one worker phase.
+fn many_events(query: &HashMap<String, String>) -> Option<(String, Vec<WorkerPhase>)> {
+    let phases = worker_phases(query);
+    match query.get("projectID") {
+        Some(id) if !phases.is_empty() => Some((id.clone(), phases)),
+        _ => None,
+    }
+}
+
+fn missing_many_events_criteria() -> Response<Body> {
+    error(
+        StatusCode::BAD_REQUEST,
+        json!({"kind": "BadRequestError", "reason": "Required fields projectID and workerPhases are missing"}),
+    )
+}
+
+// Reports whether the labels include every `k=v` pair of the query
+// parameter.
+fn has_labels(labels: Option<&HashMap<String, String>>, param: Option<&String>) -> bool {
+    let param = match param {
+        Some(p) => p,
+        None => return true,
+    };
+    param.split(',').all(|pair| {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        labels
+            .and_then(|l| l.get(k))
+            .map(|l| l == v)
+            .unwrap_or(false)
+    })
+}
+
+// Unlike labels, qualifiers have to match exactly.
+fn has_qualifiers(qualifiers: Option<&HashMap<String, String>>, param: Option<&String>) -> bool {
+    let count = qualifiers.map(|q| q.len()).unwrap_or(0);
+    match param {
+        Some(p) => has_labels(qualifiers, Some(p)) && p.split(',').count() == count,
+        None => true,
+    }
+}
+
+fn has_id(event: &Event, id: &str) -> bool {
+    event.metadata.as_ref().map(|m| m.id == id).unwrap_or(false)
+}
+
+fn principal_exists(state: &State, principal: &PrincipalReference) -> bool {
+    match principal.principal_type {
+        PrincipalType::User => state.users.contains_key(&principal.id),
+        PrincipalType::ServiceAccount => state.service_accounts.contains_key(&principal.id),
+        PrincipalType::Root => false,
+    }
+}
+
+fn principal_kind(principal: &PrincipalReference) -> &'static str {
+    match principal.principal_type {
+        PrincipalType::User => "User",
+        PrincipalType::ServiceAccount => "ServiceAccount",
+        PrincipalType::Root => "Root",
+    }
+}
+
+// Reports whether a role assignment matches the role, principalType and
+// principalID query parameters that are set.
+fn matches_query<R: Serialize>(
+    role: R,
+    principal: &PrincipalReference,
+    query: &HashMap<String, String>,
+) -> bool {
+    let role = serde_json::to_value(role).unwrap();
+    let principal_type = serde_json::to_value(principal.principal_type).unwrap();
+    query.get("role").map(|r| role == *r).unwrap_or(true)
+        && query
+            .get("principalType")
+            .map(|t| principal_type == *t)
+            .unwrap_or(true)
+        && query
+            .get("principalID")
+            .map(|id| principal.id == *id)
+            .unwrap_or(true)
+}
+
+// Returns the page of items selected by the `continue` and `limit` query
+// parameters. The continue value is the offset of the next page.
+fn list_response<T: Serialize>(items: Vec<T>, query: &HashMap<String, String>) -> Response<Body> {
+    let offset: usize = query
+        .get("continue")
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+    let limit: usize = query
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(20);
+    let total = items.len();
+    let page: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+    let next = offset + page.len();
+    let (continue_id, remaining) = if next < total {
+        (Some(next.to_string()), Some((total - next) as i64))
+    } else {
+        (None, None)
+    };
+    json_response(StatusCode::OK, &list(page, continue_id, remaining))
+}
+
+fn list<T: Serialize>(
+    items: Vec<T>,
+    continue_id: Option<String>,
+    remaining: Option<i64>,
+) -> List<T> {
+    List {
+        metadata: ListMeta {
+            continue_id,
+            remaining_item_count: remaining,
+        },
+        items: Some(items),
+    }
+}
+
+fn type_meta(kind: Kind) -> TypeMeta {
+    TypeMeta {
+        kind,
+        api_version: APIVersion::V2,
+    }
+}
+
+fn new_id() -> String {
+    let mut rng = rand::thread_rng();
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        rng.gen::<u32>(),
+        rng.gen::<u16>(),
+        rng.gen::<u16>() & 0x0fff,
+        (rng.gen::<u16>() & 0x3fff) | 0x8000,
+        rng.gen::<u64>() & 0xffff_ffff_ffff
+    )
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, serde_json::Error> {
+    serde_json::from_slice(body)
+}
+
+fn bad_request(err: serde_json::Error) -> Response<Body> {
+    error(
+        StatusCode::BAD_REQUEST,
+        json!({"kind": "BadRequestError", "reason": "Invalid request body", "details": [err.to_string()]}),
+    )
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(body).unwrap()))
+        .unwrap()
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn error(status: StatusCode, mut body: serde_json::Value) -> Response<Body> {
+    body["apiVersion"] = json!("brigade.sh/v2");
+    json_response(status, &body)
+}
+
+fn not_found(object_type: &str, id: &str) -> Response<Body> {
+    error(
+        StatusCode::NOT_FOUND,
+        json!({"kind": "NotFoundError", "type": object_type, "id": id, "reason": ""}),
+    )
+}
+
+fn conflict(object_type: &str, id: &str) -> Response<Body> {
+    error(
+        StatusCode::CONFLICT,
+        json!({"kind": "ConflictError", "type": object_type, "id": id, "reason": ""}),
+    )
+}
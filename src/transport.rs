@@ -0,0 +1,213 @@
+use crate::{client::ClientConfig, error::Result};
+use async_trait::async_trait;
+use reqwest::{header::HeaderMap, Method, Request, Response, StatusCode, Url};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+// HttpTransport sends requests built by a Client to the API server.
+//
+// The default implementation, ReqwestTransport, uses a pooled reqwest
+// client. Other implementations can be injected with `Client::with_transport`,
+// for example to serve canned responses in unit tests.
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn execute(&self, req: Request) -> Result<Response>;
+}
+
+pub struct ReqwestTransport {
+    rest: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    // Builds a reqwest client applying the connection related settings
+    // (TLS, proxy and connect timeout) from the configuration.
+    pub fn new(config: &ClientConfig) -> Result<Self> {
+        Self::with_env(config, |var| std::env::var(var).ok())
+    }
+
+    // Like new, but looks up the proxy environment variables with `env`.
+    pub(crate) fn with_env<F>(config: &ClientConfig, env: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut builder = reqwest::Client::builder()
+            .danger_accept_invalid_certs(config.allow_insecure_connections);
+        for pem in config.root_certificates.iter() {
+            for cert in reqwest::Certificate::from_pem_bundle(pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(identity) = config.client_identity.as_ref() {
+            builder = builder.identity(identity.to_reqwest()?);
+        }
+        if let Some(t) = config.connect_timeout {
+            builder = builder.connect_timeout(t);
+        }
+        match (config.proxy.as_ref(), config.no_proxy.as_ref()) {
+            (Some(p), no_proxy) => {
+                let no_proxy = match no_proxy {
+                    Some(np) => reqwest::NoProxy::from_string(np),
+                    None => reqwest::NoProxy::from_env(),
+                };
+                builder = builder.proxy(reqwest::Proxy::all(p)?.no_proxy(no_proxy));
+            }
+            // reqwest would apply NO_PROXY to the proxies it reads from the
+            // environment, so they are set up here with `no_proxy` instead.
+            (None, Some(np)) => {
+                builder = builder.no_proxy();
+                let var =
+                    |names: [&str; 2]| names.iter().find_map(|n| env(n)).filter(|v| !v.is_empty());
+                if let Some(p) = var(["HTTP_PROXY", "http_proxy"]) {
+                    let proxy = reqwest::Proxy::http(&p)?;
+                    builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_string(np)));
+                }
+                if let Some(p) = var(["HTTPS_PROXY", "https_proxy"]) {
+                    let proxy = reqwest::Proxy::https(&p)?;
+                    builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_string(np)));
+                }
+            }
+            // reqwest reads HTTP_PROXY, HTTPS_PROXY and NO_PROXY itself.
+            (None, None) => {}
+        }
+        Ok(Self {
+            rest: builder.build()?,
+        })
+    }
+
+    pub fn from_reqwest(rest: reqwest::Client) -> Self {
+        Self { rest }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn execute(&self, req: Request) -> Result<Response> {
+        Ok(self.rest.execute(req).await?)
+    }
+}
+
+// A request received by an InMemoryTransport.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RecordedRequest {
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        let body = self.body.as_deref().unwrap_or_default();
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+// InMemoryTransport answers requests with canned responses, in the order
+// they were added, and records every request it receives. It never opens
+// a connection, so clients built on it can be tested offline. When it runs
+// out of responses, it answers with 501 Not Implemented.
+//
+// Clones share the same responses and recorded requests, so a clone can be
+// kept around to inspect what a client sent.
+#[derive(Clone, Default)]
+pub struct InMemoryTransport {
+    state: Arc<Mutex<InMemoryState>>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    responses: VecDeque<(StatusCode, String)>,
+    requests: Vec<RecordedRequest>,
+}
+
+impl InMemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_response<B: Into<String>>(&self, status: StatusCode, body: B) {
+        let mut state = self.state.lock().unwrap();
+        state.responses.push_back((status, body.into()));
+    }
+
+    pub fn push_json<T: Serialize>(&self, status: StatusCode, body: &T) -> Result<()> {
+        self.push_response(status, serde_json::to_string(body)?);
+        Ok(())
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for InMemoryTransport {
+    async fn execute(&self, req: Request) -> Result<Response> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(RecordedRequest {
+            method: req.method().clone(),
+            url: req.url().clone(),
+            headers: req.headers().clone(),
+            body: req.body().and_then(|b| b.as_bytes()).map(|b| b.to_vec()),
+        });
+        let (status, body) = state.responses.pop_front().unwrap_or((
+            StatusCode::NOT_IMPLEMENTED,
+            "no canned response left".to_string(),
+        ));
+        let res = http::Response::builder()
+            .status(status)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .unwrap();
+        Ok(Response::from(res))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::projects::{Project, ProjectsClient};
+    use crate::{client::Client, error::BrigadeError};
+
+    #[tokio::test]
+    async fn test_in_memory_transport() {
+        let transport = InMemoryTransport::new();
+        let project = Project::new(
+            "hello-world".to_string(),
+            "A test project".to_string(),
+            "console.log('hello')".to_string(),
+        );
+        transport.push_json(StatusCode::OK, &project).unwrap();
+        transport.push_response(StatusCode::NOT_FOUND, r#"{"type":"Project","id":"nope"}"#);
+
+        let client = Client::with_transport(
+            "https://brigade.example.com".to_string(),
+            "projects".to_string(),
+            ClientConfig::new(),
+            Some("t0ken".to_string()),
+            Arc::new(transport.clone()),
+        );
+        let pc = ProjectsClient::from_client(&client);
+        assert_eq!(pc.get("hello-world".to_string()).await.unwrap(), project);
+        match pc.get("nope".to_string()).await.unwrap_err() {
+            BrigadeError::NotFound { id, .. } => assert_eq!(id, "nope"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+        pc.create(&project).await.unwrap_err();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://brigade.example.com/v2/projects/hello-world"
+        );
+        assert_eq!(requests[0].headers["authorization"], "Bearer t0ken");
+        assert_eq!(requests[2].method, Method::POST);
+        let sent: Project = requests[2].json().unwrap();
+        assert_eq!(sent.metadata.id, "hello-world");
+    }
+}
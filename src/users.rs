@@ -0,0 +1,185 @@
+use crate::{
+    client::{Client, ClientConfig},
+    error::Result,
+    meta::{List, ListOptions, ObjectMeta, TypeMeta},
+    pagination::{self, StreamOptions},
+};
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_with::*;
+use std::time::Duration;
+
+// User is a human principal, authenticated by a third-party identity
+// provider. Users are created by the API server the first time they log in,
+// so they can't be created through this client.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub metadata: ObjectMeta,
+    #[serde(flatten)]
+    pub type_meta: Option<TypeMeta>,
+
+    pub name: String,
+    // Set when the user is locked. A locked user can't log in.
+    pub locked: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone)]
+pub struct UsersClient {
+    client: Client,
+}
+
+impl UsersClient {
+    pub fn new(address: String, cfg: ClientConfig, token: Option<String>) -> Result<Self> {
+        let client = Client::new(address, "users".to_string(), cfg, token)?;
+        Ok(Self { client })
+    }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            client: client.with_url_path("users"),
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            client: self.client.with_timeout(timeout),
+        }
+    }
+
+    pub async fn get(&self, id: String) -> Result<User> {
+        self.client.get(id).await
+    }
+
+    pub async fn list(&self, opts: Option<ListOptions>) -> Result<List<User>> {
+        self.client.list(opts).await
+    }
+
+    // Returns a stream over all users, fetching pages lazily as the stream is
+    // consumed.
+    pub fn list_all(&self, opts: Option<ListOptions>) -> BoxStream<'static, Result<User>> {
+        let uc = self.clone();
+        let pages = pagination::pages(opts, move |opts| {
+            let uc = uc.clone();
+            async move { uc.list(Some(opts)).await }
+        });
+        pagination::items(pages, StreamOptions::default())
+    }
+
+    // Locks a user out, ending all of their sessions.
+    pub async fn lock(&self, id: String) -> Result<()> {
+        let url = self.lock_url(&id);
+        self.client
+            .send(self.client.req(Method::PUT, &url, None))
+            .await?;
+        Ok(())
+    }
+
+    // Unlocks a user, allowing them to log in again.
+    pub async fn unlock(&self, id: String) -> Result<()> {
+        let url = self.lock_url(&id);
+        self.client
+            .send(self.client.req(Method::DELETE, &url, None))
+            .await?;
+        Ok(())
+    }
+
+    // Deletes a user and ends all of their sessions. The user is created
+    // again if they log in later.
+    pub async fn delete(&self, id: String) -> Result<()> {
+        self.client.delete::<User>(id).await
+    }
+
+    fn lock_url(&self, id: &str) -> String {
+        format!(
+            "{}/v2/{}/{}/lock",
+            self.client.base_address, self.client.url_path, id
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::BrigadeError, testing::FakeServer};
+
+    #[tokio::test]
+    async fn test_get_user() {
+        let server = FakeServer::start().await;
+        server.add_user(new_user("tony@example.com"));
+        let uc = server.brigade_client().authn().users();
+        let user = uc.get("tony@example.com".to_string()).await.unwrap();
+        assert_eq!(user.name, "Tony Stark");
+        assert!(user.locked.is_none());
+
+        let err = uc.get("nope".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let server = FakeServer::start().await;
+        for i in 0..3 {
+            server.add_user(new_user(&format!("user-{}@example.com", i)));
+        }
+        let uc = server.brigade_client().authn().users();
+        let opts = ListOptions {
+            continue_id: None,
+            limit: Some(2),
+        };
+        let ul = uc.list(Some(opts)).await.unwrap();
+        assert_eq!(ul.items.unwrap().len(), 2);
+        assert_eq!(ul.metadata.remaining_item_count, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_unlock_user() {
+        let server = FakeServer::start().await;
+        server.add_user(new_user("tony@example.com"));
+        let user_client = UsersClient::new(
+            server.address(),
+            ClientConfig::new(),
+            Some(server.user_token("tony@example.com")),
+        )
+        .unwrap();
+        let uc = server.brigade_client().authn().users();
+
+        uc.lock("tony@example.com".to_string()).await.unwrap();
+        let user = uc.get("tony@example.com".to_string()).await.unwrap();
+        assert!(user.locked.is_some());
+        let err = user_client
+            .get("tony@example.com".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BrigadeError::Unauthenticated { .. }));
+
+        uc.unlock("tony@example.com".to_string()).await.unwrap();
+        let user = uc.get("tony@example.com".to_string()).await.unwrap();
+        assert!(user.locked.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let server = FakeServer::start().await;
+        server.add_user(new_user("tony@example.com"));
+        let uc = server.brigade_client().authn().users();
+        uc.delete("tony@example.com".to_string()).await.unwrap();
+        let err = uc.get("tony@example.com".to_string()).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    fn new_user(id: &str) -> User {
+        User {
+            metadata: ObjectMeta {
+                id: id.to_string(),
+                created: None,
+            },
+            type_meta: None,
+            name: "Tony Stark".to_string(),
+            locked: None,
+        }
+    }
+}
@@ -1,5 +1,4 @@
-use crate::{container::ContainerSpec, job::Job};
-use anyhow::Error;
+use crate::{container::ContainerSpec, error::Result, job::Job};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::*;
@@ -27,6 +26,8 @@ pub struct WorkerSpec {
     pub log_level: Option<LogLevel>,
     pub config_files_directory: Option<String>,
     pub default_config_files: Option<HashMap<String, String>>,
+    // How long the worker may run before it is timed out, such as `10m`.
+    pub timeout_duration: Option<String>,
 }
 
 impl WorkerSpec {
@@ -44,6 +45,7 @@ impl WorkerSpec {
             log_level: None,
             config_files_directory: None,
             default_config_files: Some(default_config_files),
+            timeout_duration: None,
         }
     }
 }
@@ -73,15 +75,15 @@ pub struct GitConfig {
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct KubernetesConfig {
-    image_pull_secrets: Option<Vec<String>>,
+    pub image_pull_secrets: Option<Vec<String>>,
 }
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct JobPolicies {
-    allow_provileged: Option<bool>,
-    allow_docker_soecket_mount: Option<bool>,
+    pub allow_privileged: Option<bool>,
+    pub allow_docker_socket_mount: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -122,15 +124,43 @@ pub enum WorkerPhase {
 }
 
 impl WorkerPhase {
-    pub fn vec_to_query_param(vec: Vec<Self>) -> Result<String, Error> {
-        // let mut phases: Vec<String> = Vec::new();
-        // for p in vec.iter() {
-        //     let str = serde_json::to_string(p)?;
-        //     phases.push(str);
-        // }
-        // vec.iter().enumerate().map(|(i, &x)| {});
-        // Ok(phases.join(","))
-        todo!()
+    // Joins phases into the comma separated list the API server expects in
+    // query strings, such as `SUCCEEDED,FAILED`.
+    pub fn vec_to_query_param(vec: Vec<Self>) -> Result<String> {
+        let mut phases: Vec<String> = Vec::new();
+        for p in vec.iter() {
+            if let serde_json::Value::String(s) = serde_json::to_value(p)? {
+                phases.push(s);
+            }
+        }
+        Ok(phases.join(","))
+    }
+
+    // Returns the phases of workers that have run to completion, one way or
+    // another.
+    pub fn terminal() -> Vec<Self> {
+        vec![
+            WorkerPhase::Aborted,
+            WorkerPhase::Canceled,
+            WorkerPhase::Failed,
+            WorkerPhase::SchedulingFailed,
+            WorkerPhase::Succeeded,
+            WorkerPhase::TimedOut,
+        ]
+    }
+
+    // Returns the phases of workers that haven't run to completion yet.
+    pub fn non_terminal() -> Vec<Self> {
+        vec![
+            WorkerPhase::Pending,
+            WorkerPhase::Starting,
+            WorkerPhase::Running,
+            WorkerPhase::Unknown,
+        ]
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        WorkerPhase::terminal().contains(self)
     }
 }
 
@@ -138,5 +168,20 @@ impl WorkerPhase {
 fn test_vec() {
     let v = vec![WorkerPhase::Succeeded];
     let str = WorkerPhase::vec_to_query_param(v).unwrap();
-    println!("{}", &str);
+    assert_eq!(str, "SUCCEEDED");
+
+    let v = vec![WorkerPhase::SchedulingFailed, WorkerPhase::TimedOut];
+    let str = WorkerPhase::vec_to_query_param(v).unwrap();
+    assert_eq!(str, "SCHEDULING_FAILED,TIMED_OUT");
+    assert_eq!(WorkerPhase::vec_to_query_param(vec![]).unwrap(), "");
+}
+
+#[test]
+fn test_terminal_phases() {
+    assert_eq!(
+        WorkerPhase::terminal().len() + WorkerPhase::non_terminal().len(),
+        10
+    );
+    assert!(WorkerPhase::TimedOut.is_terminal());
+    assert!(!WorkerPhase::Unknown.is_terminal());
 }